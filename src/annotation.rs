@@ -1,23 +1,123 @@
-use super::{Error, Result};
+use super::{Error, Result, Span};
 use std::{
+    borrow::Cow,
+    cmp::Ordering,
     fmt::{self, Display},
-    ops::Range,
+    ops::{Deref, Range},
+    str::FromStr,
 };
 
-/// Annotation severity
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Annotation severity. Severities are ordered from the least to the most severe by their
+/// [`rank`](Severity::rank). `Help` and `Note` match rustc's vocabulary and are less severe
+/// than `Info`.
+/// ```rust
+/// # use show_my_errors::Severity;
+/// const SECURITY: Severity = Severity::Custom { name: "security", rank: 450 };
+/// assert!(Severity::Warning < SECURITY && SECURITY < Severity::Error);
+/// assert_eq!(SECURITY.to_string(), "security");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Severity {
+    Help,
+    Note,
     Info,
     Warning,
     Error,
+    /// User-defined severity, e.g. `deprecation`. `name` is shown in the output, `rank`
+    /// determines how severe it is compared to other severities. Its color can be set in
+    /// [`Stylesheet::custom`](crate::Stylesheet::custom).
+    Custom {
+        name: &'static str,
+        rank: i32,
+    },
+}
+
+impl Severity {
+    /// Position of the severity on the severity scale. Built-in severities have ranks from
+    /// 100 (`Help`) to 500 (`Error`) with a step of 100, so custom ones can be put between them.
+    pub fn rank(&self) -> i32 {
+        match self {
+            Self::Help => 100,
+            Self::Note => 200,
+            Self::Info => 300,
+            Self::Warning => 400,
+            Self::Error => 500,
+            Self::Custom { rank, .. } => *rank,
+        }
+    }
+
+    /// Name of the severity, as shown by [`Display`] implementation
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Help => "help",
+            Self::Note => "note",
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+            Self::Custom { name, .. } => name,
+        }
+    }
+
+    /// The most severe built-in severity that isn't more severe than this one, or `Help` if
+    /// there is none
+    pub(crate) fn builtin(&self) -> Self {
+        [Self::Error, Self::Warning, Self::Info, Self::Note]
+            .iter()
+            .copied()
+            .find(|builtin| builtin.rank() <= self.rank())
+            .unwrap_or(Self::Help)
+    }
+}
+
+impl Ord for Severity {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let key = |severity: &Self| {
+            (
+                severity.rank(),
+                severity.name(),
+                matches!(severity, Self::Custom { .. }),
+            )
+        };
+        key(self).cmp(&key(other))
+    }
+}
+
+impl PartialOrd for Severity {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 impl Display for Severity {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::Info => f.write_str("info"),
-            Self::Warning => f.write_str("warning"),
-            Self::Error => f.write_str("error"),
+        f.write_str(self.name())
+    }
+}
+
+/// Error returned when parsing unknown [`Severity`] name
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unknown severity `{0}`, expected `help`, `note`, `info`, `warning` or `error`")]
+pub struct ParseSeverityError(pub String);
+
+/// Parse severity name, as shown by [`Display`] implementation. Names are case-insensitive;
+/// `warn` and `err` are accepted as well. Only built-in severities can be parsed.
+/// ```rust
+/// # use show_my_errors::Severity;
+/// assert_eq!("Warn".parse(), Ok(Severity::Warning));
+/// assert_eq!(Severity::Error.to_string().parse(), Ok(Severity::Error));
+/// assert!("fatal".parse::<Severity>().is_err());
+/// ```
+impl FromStr for Severity {
+    type Err = ParseSeverityError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "help" => Ok(Self::Help),
+            "note" => Ok(Self::Note),
+            "info" => Ok(Self::Info),
+            "warning" | "warn" => Ok(Self::Warning),
+            "error" | "err" => Ok(Self::Error),
+            _ => Err(ParseSeverityError(s.into())),
         }
     }
 }
@@ -35,6 +135,97 @@ pub struct Annotation {
     /// To disable this, pass a zero length range when creating the annotation.
     pub text: Option<String>,
     pub severity: Severity,
+    /// Stable code of the diagnostic shown after severity name, e.g. `E0308` in
+    /// `error[E0308]: mismatched types`. Defaults to `None`.
+    pub code: Option<String>,
+    /// Link to documentation of the diagnostic, e.g. a lint explanation page. It's shown as
+    /// the last note, `= note: see <url>`; see also
+    /// [`Stylesheet::hyperlinks`](crate::Stylesheet::hyperlinks). Defaults to `None`.
+    pub url: Option<String>,
+    /// Annotations on the same line are shown in order of descending `priority`, and in order
+    /// of addition if priorities are equal. Defaults to 0.
+    pub priority: i32,
+    /// Column shown in the location line instead of the computed one, e.g. a column after
+    /// tab expansion. Carets are still placed under the annotated fragment. Defaults to `None`.
+    pub column: Option<usize>,
+    /// Id of a label defined with
+    /// [`AnnotationList::define_label`](crate::AnnotationList::define_label). If it's set,
+    /// label text is shown instead of `text`, and all annotations but the first one using the
+    /// label refer back to it.
+    pub label_id: Option<String>,
+    /// Whether this annotation is attached to a whole line, see
+    /// [`AnnotationList::line_note`](crate::AnnotationList::line_note)
+    pub(crate) line_note: bool,
+    /// Text proposed to replace the annotated fragment, see
+    /// [`AnnotationList::replacement`](crate::AnnotationList::replacement)
+    pub(crate) replacement: Option<String>,
+    /// Secondary spans, see [`Annotation::add_secondary`]
+    pub(crate) secondary: Vec<SecondarySpan>,
+    /// Notes shown under the snippet, see [`Annotation::add_note`]
+    pub(crate) notes: Vec<Note>,
+    /// Proposed fixes, see [`Annotation::add_suggestion`]
+    pub(crate) suggestions: Vec<Suggestion>,
+}
+
+/// Proposed fix attached to an [`Annotation`]: replace the fragment of source with new text.
+/// Suggestions must not cross line boundaries, but replacement text may contain newlines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    range: Range<usize>,
+    /// Text to replace the fragment with. Empty text means removing the fragment.
+    pub replacement: String,
+}
+
+impl Suggestion {
+    /// Get range of the replaced fragment. Empty range means inserting the replacement.
+    pub fn range(&self) -> &Range<usize> {
+        &self.range
+    }
+}
+
+/// Kind of a [`Note`], shown before its text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NoteKind {
+    /// Additional information, `note: ...`
+    Note,
+    /// Advice on how to fix the problem, `help: ...`
+    Help,
+}
+
+impl From<NoteKind> for Severity {
+    fn from(kind: NoteKind) -> Self {
+        match kind {
+            NoteKind::Note => Self::Note,
+            NoteKind::Help => Self::Help,
+        }
+    }
+}
+
+/// Note or help message attached to an [`Annotation`], shown under its snippet like
+/// `= help: did you mean `Earth`?`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Note {
+    /// Kind of the note
+    pub kind: NoteKind,
+    /// Text of the note, may contain several lines
+    pub text: String,
+}
+
+/// Secondary fragment of source related to an [`Annotation`], e.g. the declaration of a
+/// variable used incorrectly. It's shown under the same header, marked with `-` instead
+/// of `^`. Secondary spans must not cross line boundaries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecondarySpan {
+    range: Range<usize>,
+    /// Label shown near the fragment
+    pub text: Option<String>,
+}
+
+impl SecondarySpan {
+    /// Get range of the fragment
+    pub fn range(&self) -> &Range<usize> {
+        &self.range
+    }
 }
 
 /// Something that can be converted to `Option<String>`.
@@ -62,10 +253,35 @@ impl AnnotationText for Option<String> {
     }
 }
 
+/// ```rust
+/// # use show_my_errors::Annotation;
+/// # use std::borrow::Cow;
+/// let name = "foo";
+/// let header: Cow<str> = if name.is_empty() {
+///     Cow::Borrowed("empty name")
+/// } else {
+///     Cow::Owned(format!("unknown name `{}`", name))
+/// };
+/// let annotation = Annotation::error(0..3, header, None).unwrap();
+/// assert_eq!(annotation.header.as_deref(), Some("unknown name `foo`"));
+/// ```
+impl AnnotationText for Cow<'_, str> {
+    fn into_option_string(self) -> Option<String> {
+        Some(self.into_owned())
+    }
+}
+
+impl AnnotationText for Box<str> {
+    fn into_option_string(self) -> Option<String> {
+        Some(self.into())
+    }
+}
+
 impl Annotation {
     /// Create new annotation.
     /// Will return [`Error::InvalidRange`] if provided range has `start > end`.
-    /// You can pass `&str`, `String` or `Option<String>` as header and text arguments.
+    /// You can pass `&str`, `String`, `Cow<str>`, `Box<str>` or `Option<String>` as header and
+    /// text arguments.
     /// ```rust
     /// # use show_my_errors::{Annotation, Severity, Error};
     /// assert_eq!(
@@ -80,11 +296,12 @@ impl Annotation {
     /// );
     /// ```
     pub fn new(
-        range: Range<usize>,
+        range: impl Into<Span>,
         severity: Severity,
         header: impl AnnotationText,
         text: impl AnnotationText,
     ) -> Result<Self> {
+        let range: Range<usize> = range.into().into();
         if range.end < range.start {
             Err(Error::InvalidRange(range.start, range.end))
         } else {
@@ -93,13 +310,73 @@ impl Annotation {
                 severity,
                 header: header.into_option_string(),
                 text: text.into_option_string(),
+                code: None,
+                url: None,
+                priority: 0,
+                column: None,
+                line_note: false,
+                replacement: None,
+                label_id: None,
+                secondary: vec![],
+                notes: vec![],
+                suggestions: vec![],
             })
         }
     }
 
+    /// Start building an annotation. This is an alternative to [`Annotation::new`] which
+    /// doesn't require passing all the fields at once. Severity defaults to
+    /// [`Severity::Error`], header and text default to `None`.
+    /// ```rust
+    /// # use show_my_errors::{Annotation, Severity};
+    /// assert_eq!(
+    ///     Annotation::builder(0..5)
+    ///         .severity(Severity::Warning)
+    ///         .header("header")
+    ///         .text("text")
+    ///         .build()
+    ///         .unwrap(),
+    ///     Annotation::new(0..5, Severity::Warning, "header", "text").unwrap(),
+    /// );
+    /// ```
+    pub fn builder(range: impl Into<Span>) -> AnnotationBuilder {
+        AnnotationBuilder {
+            range: range.into().into(),
+            severity: Severity::Error,
+            header: None,
+            text: None,
+            code: None,
+            url: None,
+            priority: 0,
+            column: None,
+            label_id: None,
+            secondary: vec![],
+            notes: vec![],
+            suggestions: vec![],
+        }
+    }
+
+    /// Create a new [`Severity::Help`] annotation
+    pub fn help(
+        range: impl Into<Span>,
+        header: impl AnnotationText,
+        text: impl AnnotationText,
+    ) -> Result<Self> {
+        Self::new(range, Severity::Help, header, text)
+    }
+
+    /// Create a new [`Severity::Note`] annotation
+    pub fn note(
+        range: impl Into<Span>,
+        header: impl AnnotationText,
+        text: impl AnnotationText,
+    ) -> Result<Self> {
+        Self::new(range, Severity::Note, header, text)
+    }
+
     /// Create a new [`Severity::Info`] annotation
     pub fn info(
-        range: Range<usize>,
+        range: impl Into<Span>,
         header: impl AnnotationText,
         text: impl AnnotationText,
     ) -> Result<Self> {
@@ -108,7 +385,7 @@ impl Annotation {
 
     /// Create a new [`Severity::Warning`] annotation
     pub fn warning(
-        range: Range<usize>,
+        range: impl Into<Span>,
         header: impl AnnotationText,
         text: impl AnnotationText,
     ) -> Result<Self> {
@@ -117,7 +394,7 @@ impl Annotation {
 
     /// Create a new [`Severity::Error`] annotation
     pub fn error(
-        range: Range<usize>,
+        range: impl Into<Span>,
         header: impl AnnotationText,
         text: impl AnnotationText,
     ) -> Result<Self> {
@@ -128,4 +405,418 @@ impl Annotation {
     pub fn range(&self) -> &Range<usize> {
         &self.range
     }
+
+    /// Check if annotation is attached to a whole line instead of a fragment, see
+    /// [`AnnotationList::line_note`](crate::AnnotationList::line_note)
+    pub fn is_line_note(&self) -> bool {
+        self.line_note
+    }
+
+    /// Short description of the annotation: its header, or its text if there is no header.
+    /// Serializers such as JSON and SARIF use it as the message of the diagnostic.
+    /// ```rust
+    /// # use show_my_errors::Annotation;
+    /// let annotation = Annotation::error(0..5, "header", "text").unwrap();
+    /// assert_eq!(annotation.message(), Some("header"));
+    /// let annotation = Annotation::error(0..5, None, "text").unwrap();
+    /// assert_eq!(annotation.message(), Some("text"));
+    /// ```
+    pub fn message(&self) -> Option<&str> {
+        self.header.as_deref().or(self.text.as_deref())
+    }
+
+    /// Get text proposed to replace the annotated fragment, see
+    /// [`AnnotationList::replacement`](crate::AnnotationList::replacement)
+    pub fn replacement(&self) -> Option<&str> {
+        self.replacement.as_deref()
+    }
+
+    /// Change annotations range.
+    /// Will return [`Error::InvalidRange`] and leave the annotation unchanged if provided range
+    /// has `start > end`. Checks that depend on the source (e.g. that range doesn't cross line
+    /// boundary) are performed when annotation is added to
+    /// [`AnnotationList`](crate::AnnotationList).
+    /// ```rust
+    /// # use show_my_errors::{Annotation, Error};
+    /// let mut annotation = Annotation::info(0..5, "header", "text").unwrap();
+    /// annotation.set_range(10..12).unwrap();
+    /// assert_eq!(annotation.range(), &(10..12));
+    /// assert_eq!(annotation.set_range(5..0), Err(Error::InvalidRange(5, 0)));
+    /// assert_eq!(annotation.range(), &(10..12));
+    /// ```
+    pub fn set_range(&mut self, range: impl Into<Span>) -> Result<()> {
+        let range: Range<usize> = range.into().into();
+        if range.end < range.start {
+            Err(Error::InvalidRange(range.start, range.end))
+        } else {
+            self.range = range;
+            Ok(())
+        }
+    }
+
+    /// Attach a secondary span with its own label to the annotation, like the "value moved
+    /// here" part of the rustc "use of moved value" error. Secondary spans may be on other
+    /// lines than the annotation itself; these lines are shown too, in source order.
+    /// Will return [`Error::InvalidRange`] if provided range has `start > end`. Other checks
+    /// are performed when annotation is added to [`AnnotationList`](crate::AnnotationList).
+    /// ```rust
+    /// # use show_my_errors::{Annotation, AnnotationList};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("main.rs", "let y = x;\nx.foo();\n");
+    /// let mut annotation = Annotation::error(11..12, "use of moved value: `x`", "value used here after move")?;
+    /// annotation.add_secondary(8..9, "value moved here")?;
+    /// list.add(annotation)?;
+    /// assert_eq!(
+    ///     list.to_string()?,
+    ///     r#"error: use of moved value: `x`
+    ///   --> main.rs:2:1
+    ///    |
+    ///  1 | let y = x;
+    ///    |         - value moved here
+    ///  2 | x.foo();
+    ///    | ^ value used here after move
+    /// "#
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_secondary(
+        &mut self,
+        range: impl Into<Span>,
+        text: impl AnnotationText,
+    ) -> Result<&mut Self> {
+        let range: Range<usize> = range.into().into();
+        if range.end < range.start {
+            return Err(Error::InvalidRange(range.start, range.end));
+        }
+        self.secondary.push(SecondarySpan {
+            range,
+            text: text.into_option_string(),
+        });
+        Ok(self)
+    }
+
+    /// Get secondary spans of the annotation, see [`Annotation::add_secondary`]
+    pub fn secondary_spans(&self) -> &[SecondarySpan] {
+        &self.secondary
+    }
+
+    /// Attach a `note:` message to the annotation. Notes are shown under the snippet in the
+    /// order they're added, so one diagnostic can carry additional information without
+    /// creating another top-level annotation.
+    /// ```rust
+    /// # use show_my_errors::{Annotation, AnnotationList};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("hello.txt", "Hello wrold!");
+    /// let mut annotation = Annotation::error(6..11, "unknown planet", None)?;
+    /// annotation
+    ///     .add_note("planets are looked up in the solar system")
+    ///     .add_help("did you mean `world`?");
+    /// list.add(annotation)?;
+    /// assert!(list.to_string()?.ends_with(
+    ///     "   |       ^^^^^\n   |\n   = note: planets are looked up in the solar system\n   = help: did you mean `world`?\n"
+    /// ));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_note(&mut self, text: impl Into<String>) -> &mut Self {
+        self.notes.push(Note {
+            kind: NoteKind::Note,
+            text: text.into(),
+        });
+        self
+    }
+
+    /// Attach a `help:` message to the annotation, see [`Annotation::add_note`]
+    pub fn add_help(&mut self, text: impl Into<String>) -> &mut Self {
+        self.notes.push(Note {
+            kind: NoteKind::Help,
+            text: text.into(),
+        });
+        self
+    }
+
+    /// Get notes and help messages of the annotation, see [`Annotation::add_note`]
+    pub fn notes(&self) -> &[Note] {
+        &self.notes
+    }
+
+    /// Attach a proposed fix: replace `range` of source with `replacement`. The patched line
+    /// is shown under the snippet, with the new text marked, like rustc does for its
+    /// suggestions. Will return [`Error::InvalidRange`] if provided range has `start > end`.
+    /// Other checks are performed when annotation is added to
+    /// [`AnnotationList`](crate::AnnotationList).
+    /// ```rust
+    /// # use show_my_errors::{Annotation, AnnotationList};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("hello.txt", "Hello wrold!");
+    /// let mut annotation = Annotation::error(6..11, "unknown planet", None)?;
+    /// annotation.add_suggestion(6..11, "world")?;
+    /// list.add(annotation)?;
+    /// assert_eq!(
+    ///     list.to_string()?,
+    ///     r#"error: unknown planet
+    ///   --> hello.txt:1:7
+    ///    |
+    ///  1 | Hello wrold!
+    ///    |       ^^^^^
+    /// help: replace with `world`
+    ///    |
+    ///  1 | Hello world!
+    ///    |       ~~~~~
+    /// "#
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_suggestion(
+        &mut self,
+        range: impl Into<Span>,
+        replacement: impl Into<String>,
+    ) -> Result<&mut Self> {
+        let range: Range<usize> = range.into().into();
+        if range.end < range.start {
+            return Err(Error::InvalidRange(range.start, range.end));
+        }
+        self.suggestions.push(Suggestion {
+            range,
+            replacement: replacement.into(),
+        });
+        Ok(self)
+    }
+
+    /// Get proposed fixes of the annotation, see [`Annotation::add_suggestion`]
+    pub fn suggestions(&self) -> &[Suggestion] {
+        &self.suggestions
+    }
+
+    /// Check if ranges of two annotations overlap. Zero length ranges overlap only with ranges
+    /// strictly containing them.
+    pub fn overlaps(&self, other: &Annotation) -> bool {
+        self.range.start < other.range.end && other.range.start < self.range.end
+    }
+
+    /// Check if two annotations have the same range and severity, ignoring their header,
+    /// text and other fields. Useful to find the same problem reported with different wording.
+    pub fn same_location(&self, other: &Annotation) -> bool {
+        self.range == other.range && self.severity == other.severity
+    }
+}
+
+/// Handle of an annotation in [`AnnotationList`](crate::AnnotationList), returned by
+/// [`AnnotationList::push`](crate::AnnotationList::push). Handles stay valid until
+/// annotations are removed from the list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AnnotationId {
+    pub(crate) line: usize,
+    pub(crate) index: usize,
+}
+
+/// Mutable access to an annotation in [`AnnotationList`](crate::AnnotationList), returned by
+/// [`AnnotationList::annotation_mut`](crate::AnnotationList::annotation_mut). Range can
+/// only be changed with [`.set_range()`](AnnotationMut::set_range), which checks that the
+/// annotation stays on its lines.
+#[derive(Debug)]
+pub struct AnnotationMut<'a> {
+    pub(crate) annotation: &'a mut Annotation,
+    /// Range of the line content, including trailing newline
+    pub(crate) line: Range<usize>,
+    /// End of the last line covered by the annotation, including trailing newline
+    pub(crate) end: usize,
+}
+
+impl Deref for AnnotationMut<'_> {
+    type Target = Annotation;
+
+    fn deref(&self) -> &Annotation {
+        self.annotation
+    }
+}
+
+impl AnnotationMut<'_> {
+    /// Set annotation header
+    pub fn set_header(&mut self, header: impl AnnotationText) -> &mut Self {
+        self.annotation.header = header.into_option_string();
+        self
+    }
+
+    /// Set annotation text
+    pub fn set_text(&mut self, text: impl AnnotationText) -> &mut Self {
+        self.annotation.text = text.into_option_string();
+        self
+    }
+
+    /// Set annotation severity
+    pub fn set_severity(&mut self, severity: Severity) -> &mut Self {
+        self.annotation.severity = severity;
+        self
+    }
+
+    /// Set code of the diagnostic, see [`Annotation::code`]
+    pub fn set_code(&mut self, code: impl AnnotationText) -> &mut Self {
+        self.annotation.code = code.into_option_string();
+        self
+    }
+
+    /// Set link to documentation, see [`Annotation::url`]
+    pub fn set_url(&mut self, url: impl AnnotationText) -> &mut Self {
+        self.annotation.url = url.into_option_string();
+        self
+    }
+
+    /// Set annotation priority
+    pub fn set_priority(&mut self, priority: i32) -> &mut Self {
+        self.annotation.priority = priority;
+        self
+    }
+
+    /// Change annotation range. Returns [`Error::InvalidRange`] if range has `start > end`
+    /// and [`Error::MultilineRange`] if it doesn't start on the first line of the annotation
+    /// or ends after its last line. Multi-line annotations can be re-ranged within the lines
+    /// they already cover. On error the annotation is left unchanged.
+    /// ```rust
+    /// # use show_my_errors::{Annotation, AnnotationList, Error};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("main.rs", "fn f() {\n}\nfn g() {}\n");
+    /// let id = list.push_multiline(Annotation::error(0..10, "unclosed", None)?)?;
+    /// let mut annotation = list.annotation_mut(id).unwrap();
+    /// annotation.set_range(3..11)?;
+    /// assert_eq!(annotation.range(), &(3..11));
+    /// assert_eq!(annotation.set_range(3..12), Err(Error::MultilineRange(3, 12)));
+    /// assert_eq!(annotation.set_range(10..11), Err(Error::MultilineRange(10, 11)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_range(&mut self, range: impl Into<Span>) -> Result<()> {
+        let range: Range<usize> = range.into().into();
+        if range.end < range.start {
+            return Err(Error::InvalidRange(range.start, range.end));
+        }
+        // Like in `.add()`, end of a line is the start of the next one, unless the line is empty
+        let after_line =
+            range.start > self.line.end || (range.start == self.line.end && !self.line.is_empty());
+        if range.start < self.line.start || after_line || range.end > self.end {
+            return Err(Error::MultilineRange(range.start, range.end));
+        }
+        self.annotation.set_range(range)
+    }
+}
+
+/// Builder for [`Annotation`], created by [`Annotation::builder`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnotationBuilder {
+    range: Range<usize>,
+    severity: Severity,
+    header: Option<String>,
+    text: Option<String>,
+    code: Option<String>,
+    url: Option<String>,
+    priority: i32,
+    column: Option<usize>,
+    label_id: Option<String>,
+    secondary: Vec<(Range<usize>, Option<String>)>,
+    notes: Vec<Note>,
+    suggestions: Vec<(Range<usize>, String)>,
+}
+
+impl AnnotationBuilder {
+    /// Set annotation severity
+    pub fn severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Set annotation header
+    pub fn header(mut self, header: impl AnnotationText) -> Self {
+        self.header = header.into_option_string();
+        self
+    }
+
+    /// Set annotation text
+    pub fn text(mut self, text: impl AnnotationText) -> Self {
+        self.text = text.into_option_string();
+        self
+    }
+
+    /// Set code of the diagnostic, see [`Annotation::code`]
+    pub fn code(mut self, code: impl AnnotationText) -> Self {
+        self.code = code.into_option_string();
+        self
+    }
+
+    /// Set link to documentation, see [`Annotation::url`]
+    pub fn url(mut self, url: impl AnnotationText) -> Self {
+        self.url = url.into_option_string();
+        self
+    }
+
+    /// Set annotation priority
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Set column shown in the location line
+    pub fn column(mut self, column: usize) -> Self {
+        self.column = Some(column);
+        self
+    }
+
+    /// Set id of the label used for annotation text
+    pub fn label_id(mut self, label_id: impl Into<String>) -> Self {
+        self.label_id = Some(label_id.into());
+        self
+    }
+
+    /// Add a secondary span, see [`Annotation::add_secondary`]
+    pub fn secondary(mut self, range: impl Into<Span>, text: impl AnnotationText) -> Self {
+        self.secondary
+            .push((range.into().into(), text.into_option_string()));
+        self
+    }
+
+    /// Add a `note:` message, see [`Annotation::add_note`]
+    pub fn note(mut self, text: impl Into<String>) -> Self {
+        self.notes.push(Note {
+            kind: NoteKind::Note,
+            text: text.into(),
+        });
+        self
+    }
+
+    /// Add a `help:` message, see [`Annotation::add_help`]
+    pub fn help(mut self, text: impl Into<String>) -> Self {
+        self.notes.push(Note {
+            kind: NoteKind::Help,
+            text: text.into(),
+        });
+        self
+    }
+
+    /// Add a proposed fix, see [`Annotation::add_suggestion`]
+    pub fn suggestion(mut self, range: impl Into<Span>, replacement: impl Into<String>) -> Self {
+        self.suggestions
+            .push((range.into().into(), replacement.into()));
+        self
+    }
+
+    /// Create an annotation.
+    /// Will return [`Error::InvalidRange`] if provided range or range of a secondary span or
+    /// suggestion has `start > end`.
+    pub fn build(self) -> Result<Annotation> {
+        let mut annotation = Annotation::new(self.range, self.severity, self.header, self.text)?;
+        annotation.code = self.code;
+        annotation.url = self.url;
+        annotation.priority = self.priority;
+        annotation.column = self.column;
+        annotation.label_id = self.label_id;
+        annotation.notes = self.notes;
+        for (range, text) in self.secondary {
+            annotation.add_secondary(range, text)?;
+        }
+        for (range, replacement) in self.suggestions {
+            annotation.add_suggestion(range, replacement)?;
+        }
+        Ok(annotation)
+    }
 }
@@ -0,0 +1,106 @@
+use crate::{AnnotationList, MultiFileReport, Severity};
+use std::fmt::Write;
+
+const HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<checkstyle version=\"4.3\">\n";
+const FOOTER: &str = "</checkstyle>\n";
+
+/// Escape string for use in XML text or attribute value
+pub(crate) fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\r' => escaped.push_str("&#13;"),
+            '\t' => escaped.push_str("&#9;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl AnnotationList<'_> {
+    /// Serialize annotations to [Checkstyle](https://checkstyle.org) XML report with a single
+    /// `<file>` element, using [annotation message](crate::Annotation::message) as the error
+    /// message and [code](crate::Annotation::code) as its source. Errors and
+    /// warnings keep their severity, everything else becomes `info`. Columns are counted in
+    /// Unicode code points.
+    /// ```rust
+    /// # use show_my_errors::AnnotationList;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("hello.txt", "Hello world!");
+    /// list.warning(4..7, "punctuation problem", "you probably forgot a comma")?;
+    /// assert_eq!(list.to_checkstyle_xml(), r#"<?xml version="1.0" encoding="UTF-8"?>
+    /// <checkstyle version="4.3">
+    /// <file name="hello.txt">
+    /// <error line="1" column="5" severity="warning" message="punctuation problem"/>
+    /// </file>
+    /// </checkstyle>
+    /// "#);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_checkstyle_xml(&self) -> String {
+        let mut xml = HEADER.to_owned();
+        let _ = writeln!(xml, r#"<file name="{}">"#, escape_xml(&self.filename));
+        self.checkstyle_errors(&mut xml);
+        xml.push_str("</file>\n");
+        xml.push_str(FOOTER);
+        xml
+    }
+
+    fn checkstyle_errors(&self, xml: &mut String) {
+        for (idx, annotation) in self.blocks() {
+            let _ = write!(xml, r#"<error line="{}""#, idx + 1);
+            if !annotation.is_line_note() {
+                let column = self.lines[idx].char_column(annotation.range().start);
+                let _ = write!(xml, r#" column="{}""#, column);
+            }
+            let severity = match annotation.severity.builtin() {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                _ => "info",
+            };
+            let message = annotation.message().unwrap_or_default();
+            let _ = write!(
+                xml,
+                r#" severity="{}" message="{}""#,
+                severity,
+                escape_xml(message)
+            );
+            if let Some(code) = &annotation.code {
+                let _ = write!(xml, r#" source="{}""#, escape_xml(code));
+            }
+            xml.push_str("/>\n");
+        }
+    }
+}
+
+impl MultiFileReport<'_> {
+    /// Serialize annotations of all files to Checkstyle XML report, see
+    /// [`AnnotationList::to_checkstyle_xml`]. There is one `<file>` element per filename, in
+    /// order of names; lists with the same filename are grouped into one element.
+    pub fn to_checkstyle_xml(&self) -> String {
+        let mut xml = HEADER.to_owned();
+        let lists = self.sorted_lists();
+        for (list_idx, list) in lists.iter().enumerate() {
+            let first_of_file = list_idx == 0 || lists[list_idx - 1].filename != list.filename;
+            let last_of_file = lists
+                .get(list_idx + 1)
+                .map_or(true, |next| next.filename != list.filename);
+            if first_of_file {
+                let _ = writeln!(xml, r#"<file name="{}">"#, escape_xml(&list.filename));
+            }
+            list.checkstyle_errors(&mut xml);
+            if last_of_file {
+                xml.push_str("</file>\n");
+            }
+        }
+        xml.push_str(FOOTER);
+        xml
+    }
+}
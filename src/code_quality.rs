@@ -0,0 +1,94 @@
+use crate::{json::escape_json, AnnotationList, MultiFileReport, Severity};
+use std::collections::HashMap;
+
+/// 64-bit FNV-1a hash, used instead of [`std::hash`] hashers since fingerprints must be the
+/// same across Rust versions
+fn fnv1a(parts: &[&str]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    for part in parts {
+        // Separator, so that e.g. `["ab", "c"]` and `["a", "bc"]` differ
+        for byte in part.bytes().chain(std::iter::once(0)) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+impl AnnotationList<'_> {
+    /// Serialize annotations to [GitLab Code Quality][code-quality] report, i.e. a JSON array of
+    /// Code Climate issues. Description is [annotation message](crate::Annotation::message);
+    /// check name is annotation [code](crate::Annotation::code), or severity name if
+    /// there is no code. Errors are `major` issues, warnings are `minor` ones, everything else
+    /// is `info`. Fingerprint depends on filename, check name, description and content of the
+    /// annotated line, but not on its number, so issues keep their identity when code around
+    /// them changes.
+    ///
+    /// Requires `json` feature.
+    ///
+    /// [code-quality]: https://docs.gitlab.com/ee/ci/testing/code_quality.html#implement-a-custom-tool
+    /// ```rust
+    /// # use show_my_errors::AnnotationList;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("hello.txt", "Hello world!");
+    /// list.warning(4..7, "punctuation problem", "you probably forgot a comma")?;
+    /// let report = list.to_code_quality_json();
+    /// assert!(report.starts_with(r#"[{"description":"punctuation problem","check_name":"warning","#));
+    /// assert!(report.ends_with(r#""severity":"minor","location":{"path":"hello.txt","lines":{"begin":1,"end":1}}}]"#));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_code_quality_json(&self) -> String {
+        let mut issues = vec![];
+        self.code_quality_issues(&mut issues, &mut HashMap::new());
+        format!("[{}]", issues.join(","))
+    }
+
+    /// Serialize issues, counting fingerprints in `seen` to make them unique
+    fn code_quality_issues(&self, issues: &mut Vec<String>, seen: &mut HashMap<u64, usize>) {
+        for (idx, annotation) in self.blocks() {
+            let description = annotation.message().unwrap_or_default();
+            let severity_name = annotation.severity.to_string();
+            let check_name = annotation.code.as_deref().unwrap_or(&severity_name);
+            let severity = match annotation.severity.builtin() {
+                Severity::Error => "major",
+                Severity::Warning => "minor",
+                _ => "info",
+            };
+            let content = self.lines[idx].content.trim();
+            let hash = fnv1a(&[&self.filename, check_name, description, content]);
+            // Same issues on lines with the same content are told apart by their order
+            let occurrence = seen.entry(hash).or_insert(0);
+            *occurrence += 1;
+            let fingerprint = format!("{:016x}{:04x}", hash, *occurrence - 1);
+            issues.push(format!(
+                concat!(
+                    r#"{{"description":"{}","check_name":"{}","fingerprint":"{}","#,
+                    r#""severity":"{}","location":{{"path":"{}","lines":{{"begin":{},"end":{}}}}}}}"#,
+                ),
+                escape_json(description),
+                escape_json(check_name),
+                fingerprint,
+                severity,
+                escape_json(&self.filename.replace('\\', "/")),
+                idx + 1,
+                self.end_line_index(idx, annotation) + 1,
+            ));
+        }
+    }
+}
+
+impl MultiFileReport<'_> {
+    /// Serialize annotations of all files to GitLab Code Quality report, see
+    /// [`AnnotationList::to_code_quality_json`]. Files are ordered by their names.
+    ///
+    /// Requires `json` feature.
+    pub fn to_code_quality_json(&self) -> String {
+        let mut issues = vec![];
+        let mut seen = HashMap::new();
+        for list in self.sorted_lists() {
+            list.code_quality_issues(&mut issues, &mut seen);
+        }
+        format!("[{}]", issues.join(","))
+    }
+}
@@ -0,0 +1,67 @@
+use std::{
+    env,
+    sync::atomic::{AtomicU8, Ordering},
+};
+use termcolor::ColorChoice;
+
+/// Process-wide color choice, encoded by [`encode`]. `0` means it's not set.
+static DEFAULT_COLOR_CHOICE: AtomicU8 = AtomicU8::new(0);
+
+fn encode(color_choice: Option<ColorChoice>) -> u8 {
+    match color_choice {
+        None => 0,
+        Some(ColorChoice::Always) => 1,
+        Some(ColorChoice::AlwaysAnsi) => 2,
+        Some(ColorChoice::Auto) => 3,
+        Some(ColorChoice::Never) => 4,
+    }
+}
+
+fn decode(value: u8) -> Option<ColorChoice> {
+    match value {
+        1 => Some(ColorChoice::Always),
+        2 => Some(ColorChoice::AlwaysAnsi),
+        3 => Some(ColorChoice::Auto),
+        4 => Some(ColorChoice::Never),
+        _ => None,
+    }
+}
+
+/// Set process-wide [`ColorChoice`] used by
+/// [`.show_stdout()`](crate::AnnotationList::show_stdout),
+/// [`.show_stderr()`](crate::AnnotationList::show_stderr) and their `_colored` variants, e.g.
+/// to wire a `--color=always|never|auto` flag in one place. `None` restores the default
+/// detection.
+///
+/// Color choice for these methods is decided in this order:
+/// 1. explicit per-call choice, see
+///    [`.show_stdout_with_color()`](crate::AnnotationList::show_stdout_with_color);
+/// 2. choice set by this function;
+/// 3. [`ColorChoice::Never`] if the `NO_COLOR` environment variable is set and not empty;
+/// 4. [`ColorChoice::Auto`] if the stream is a TTY, [`ColorChoice::Never`] otherwise.
+/// ```rust
+/// # use show_my_errors::set_default_color_choice;
+/// # use termcolor::ColorChoice;
+/// set_default_color_choice(Some(ColorChoice::Never));
+/// ```
+pub fn set_default_color_choice(color_choice: Option<ColorChoice>) {
+    DEFAULT_COLOR_CHOICE.store(encode(color_choice), Ordering::Relaxed);
+}
+
+/// Get process-wide [`ColorChoice`] set by [`set_default_color_choice`]
+pub fn default_color_choice() -> Option<ColorChoice> {
+    decode(DEFAULT_COLOR_CHOICE.load(Ordering::Relaxed))
+}
+
+/// Decide color choice for a stream, see [`set_default_color_choice`] for precedence
+pub(crate) fn resolve_color_choice(explicit: Option<ColorChoice>, is_tty: bool) -> ColorChoice {
+    if let Some(color_choice) = explicit.or_else(default_color_choice) {
+        color_choice
+    } else if env::var_os("NO_COLOR").map_or(false, |value| !value.is_empty()) {
+        ColorChoice::Never
+    } else if is_tty {
+        ColorChoice::Auto
+    } else {
+        ColorChoice::Never
+    }
+}
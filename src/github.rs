@@ -0,0 +1,82 @@
+use crate::{AnnotationList, MultiFileReport, Severity};
+use std::fmt::Write;
+
+/// Escape message of a workflow command
+fn escape_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape property value of a workflow command
+fn escape_property(s: &str) -> String {
+    escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
+
+impl AnnotationList<'_> {
+    /// Format annotations as [GitHub Actions workflow commands][commands], one
+    /// `::error file=...,line=...,col=...::message` line per annotation, so they are shown
+    /// inline in pull requests. Errors and warnings keep their severity, everything else
+    /// becomes a notice. [Message](crate::Annotation::message) of the annotation is used as the
+    /// command message and its [code](crate::Annotation::code) as the title. Columns are
+    /// counted in Unicode code points.
+    ///
+    /// [commands]: https://docs.github.com/en/actions/using-workflows/workflow-commands-for-github-actions
+    /// ```rust
+    /// # use show_my_errors::AnnotationList;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("hello.txt", "Hello world!");
+    /// list.warning(4..7, "punctuation problem", "you probably forgot a comma")?;
+    /// assert_eq!(
+    ///     list.to_github_actions(),
+    ///     "::warning file=hello.txt,line=1,col=5,endColumn=8::punctuation problem\n",
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_github_actions(&self) -> String {
+        let mut output = String::new();
+        self.github_actions_into(&mut output);
+        output
+    }
+
+    fn github_actions_into(&self, output: &mut String) {
+        let file = escape_property(&self.filename);
+        for (idx, annotation) in self.blocks() {
+            let command = match annotation.severity.builtin() {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                _ => "notice",
+            };
+            let _ = write!(output, "::{} file={},line={}", command, file, idx + 1);
+            if !annotation.is_line_note() {
+                let range = annotation.range();
+                let end_idx = self.end_line_index(idx, annotation);
+                let _ = write!(output, ",col={}", self.lines[idx].char_column(range.start));
+                if end_idx == idx {
+                    let end_column = self.lines[idx].char_column(range.end);
+                    let _ = write!(output, ",endColumn={}", end_column);
+                } else {
+                    let _ = write!(output, ",endLine={}", end_idx + 1);
+                }
+            }
+            if let Some(code) = &annotation.code {
+                let _ = write!(output, ",title={}", escape_property(code));
+            }
+            let message = annotation.message().unwrap_or_default();
+            let _ = writeln!(output, "::{}", escape_data(message));
+        }
+    }
+}
+
+impl MultiFileReport<'_> {
+    /// Format annotations of all files as GitHub Actions workflow commands, see
+    /// [`AnnotationList::to_github_actions`]. Files are ordered by their names.
+    pub fn to_github_actions(&self) -> String {
+        let mut output = String::new();
+        for list in self.sorted_lists() {
+            list.github_actions_into(&mut output);
+        }
+        output
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{checkstyle::escape_xml, AnnotationList, RenderEvent, Severity, StyleRole, Stylesheet};
+
+/// Default CSS for [`AnnotationList::to_html`] output, with rustc-like colors
+/// ```rust
+/// # use show_my_errors::{AnnotationList, DEFAULT_HTML_CSS};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut list = AnnotationList::new("hello.txt", "Hello world!");
+/// list.warning(4..7, "punctuation problem", "you probably forgot a comma")?;
+/// let page = format!("<style>{}</style>{}", DEFAULT_HTML_CSS, list.to_html());
+/// # Ok(())
+/// # }
+/// ```
+pub const DEFAULT_HTML_CSS: &str = "\
+.smy-diagnostic { font-family: monospace; }
+.smy-help { color: #0ea5b7; font-weight: bold; }
+.smy-note { color: #16a34a; font-weight: bold; }
+.smy-info { font-weight: bold; }
+.smy-warning { color: #ca8a04; font-weight: bold; }
+.smy-error { color: #dc2626; font-weight: bold; }
+.smy-underline { text-decoration: underline; }
+.smy-line-number { color: #2563eb; font-weight: bold; }
+.smy-filename { font-weight: bold; }
+.smy-link { color: inherit; }
+";
+
+/// CSS classes of the text with given role
+fn role_classes(role: StyleRole) -> String {
+    // Custom severity names may contain anything, so they're made a single escaped class
+    let severity = |severity: Severity| {
+        format!(
+            "smy-{}",
+            escape_xml(&severity.name().replace(char::is_whitespace, "-"))
+        )
+    };
+    match role {
+        StyleRole::Severity(sev)
+        | StyleRole::Caret(sev)
+        | StyleRole::CaretEdge(sev)
+        | StyleRole::CaretMiddle(sev) => severity(sev),
+        StyleRole::Underline(sev) => format!("{} smy-underline", severity(sev)),
+        StyleRole::Code(sev) => format!("{} smy-code", severity(sev)),
+        StyleRole::Link => "smy-link".into(),
+        StyleRole::LineNumber => "smy-line-number".into(),
+        StyleRole::Filename => "smy-filename".into(),
+        StyleRole::Directory => "smy-directory".into(),
+        StyleRole::Content => "smy-content".into(),
+    }
+}
+
+impl AnnotationList<'_> {
+    /// Render the message as HTML: a `<pre class="smy-diagnostic">` element with every piece
+    /// of text wrapped in a `<span>` with a class by its [`StyleRole`], e.g.
+    /// `<span class="smy-error">`, so it can be styled with CSS. Severity classes are
+    /// `smy-` followed by severity name, with whitespace replaced by `-`. Documentation links
+    /// are `<a class="smy-link">` elements if they're `http` or `https` URLs, and plain
+    /// `<span class="smy-link">` elements otherwise. See [`DEFAULT_HTML_CSS`] for rustc-like
+    /// colors.
+    /// ```rust
+    /// # use show_my_errors::AnnotationList;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("hello.txt", "Hello <world>!");
+    /// list.warning(6..13, "tag", None)?;
+    /// let html = list.to_html();
+    /// assert!(html.starts_with(r#"<pre class="smy-diagnostic"><span class="smy-warning">warning: tag</span>"#));
+    /// assert!(html.contains(r#"<span class="smy-content">Hello &lt;world&gt;!</span>"#));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_html(&self) -> String {
+        events_to_html(&self.render_events(&Stylesheet::monochrome()))
+    }
+}
+
+/// Check that a link can be safely put into `href`, i.e. that it's not e.g. `javascript:`
+fn is_web_url(url: &str) -> bool {
+    let url = url.trim_start().to_ascii_lowercase();
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+/// Write rendered lines as HTML. Adjacent segments with the same role are already merged by
+/// the layout, so every segment becomes a single element.
+fn events_to_html(events: &[RenderEvent]) -> String {
+    let mut html = String::from(r#"<pre class="smy-diagnostic">"#);
+    for (event_idx, event) in events.iter().enumerate() {
+        if event_idx != 0 {
+            html.push('\n');
+        }
+        for segment in event.segments() {
+            let text = escape_xml(&segment.text);
+            if segment.role == StyleRole::Link && is_web_url(&segment.text) {
+                html.push_str(&format!(
+                    r#"<a class="smy-link" href="{}">{}</a>"#,
+                    text, text
+                ));
+            } else {
+                html.push_str(&format!(
+                    r#"<span class="{}">{}</span>"#,
+                    role_classes(segment.role),
+                    text
+                ));
+            }
+        }
+    }
+    html.push_str("</pre>\n");
+    html
+}
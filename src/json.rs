@@ -0,0 +1,213 @@
+use crate::{render::write_events, AnnotationList, NoteKind, RenderOptions, Severity, Stylesheet};
+use std::{fmt::Write, iter, ops::Range};
+
+/// Escape string for use inside JSON string literal
+pub(crate) fn escape_json(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Quote string as JSON string literal, or `null` if there is none
+fn json_string(s: Option<&str>) -> String {
+    s.map_or_else(|| "null".into(), |s| format!(r#""{}""#, escape_json(s)))
+}
+
+/// Level of the diagnostic, as rustc names it
+fn rustc_level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Help => "help",
+        Severity::Note | Severity::Info => "note",
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+        Severity::Custom { .. } => rustc_level(severity.builtin()),
+    }
+}
+
+/// Child diagnostic without code and rendered text, e.g. a note
+fn child_json(level: &str, message: &str, spans: &[String]) -> String {
+    format!(
+        r#"{{"message":"{}","code":null,"level":"{}","spans":[{}],"children":[],"rendered":null}}"#,
+        escape_json(message),
+        level,
+        spans.join(","),
+    )
+}
+
+impl AnnotationList<'_> {
+    /// Serialize annotations in the format of `rustc --error-format=json`: one JSON object per
+    /// line for every annotation, in the order they are shown, with
+    /// [annotation message](crate::Annotation::message) as the message. The text is also used
+    /// as the label of the primary span. Secondary spans are included as non-primary spans,
+    /// notes, suggestions and [documentation link](crate::Annotation::url) as children. `rendered` field holds monochrome
+    /// rendering of the annotation. Columns are counted in Unicode code points, as rustc does.
+    ///
+    /// Requires `json` feature.
+    /// ```rust
+    /// # use show_my_errors::{Annotation, AnnotationList};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("main.rs", "let x: i32 = \"1\";");
+    /// list.add(Annotation::builder(13..16).header("mismatched types").code("E0308").build()?)?;
+    /// let json = list.to_json();
+    /// assert!(json.starts_with(r#"{"$message_type":"diagnostic","message":"mismatched types","#));
+    /// assert!(json.contains(r#""code":{"code":"E0308","explanation":null},"level":"error""#));
+    /// assert!(json.contains(r#""byte_start":13,"byte_end":16,"line_start":1,"line_end":1"#));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_json(&self) -> String {
+        let stylesheet = Stylesheet::monochrome();
+        let options = RenderOptions::default();
+        let mut json = String::new();
+        for (idx, annotation) in self.blocks() {
+            let range = annotation.range();
+            let message = annotation.message().unwrap_or_default();
+            let code = annotation.code.as_deref().map_or_else(
+                || "null".into(),
+                |code| format!(r#"{{"code":"{}","explanation":null}}"#, escape_json(code)),
+            );
+
+            let end_idx = self.end_line_index(idx, annotation);
+            let mut spans = vec![self.span_json(
+                idx..end_idx + 1,
+                range,
+                true,
+                annotation.text.as_deref(),
+                None,
+            )];
+            for span in annotation.secondary_spans() {
+                if let Ok(idx) = self.line_index_for(span.range().start) {
+                    spans.push(self.span_json(
+                        idx..idx + 1,
+                        span.range(),
+                        false,
+                        span.text.as_deref(),
+                        None,
+                    ));
+                }
+            }
+
+            let mut children: Vec<_> = annotation
+                .notes()
+                .iter()
+                .map(|note| {
+                    let level = match note.kind {
+                        NoteKind::Note => "note",
+                        NoteKind::Help => "help",
+                    };
+                    child_json(level, &note.text, &[])
+                })
+                .collect();
+            for suggestion in annotation.suggestions() {
+                let range = suggestion.range();
+                let idx = match self.line_index_for(range.start) {
+                    Ok(idx) => idx,
+                    Err(_) => continue,
+                };
+                let message = if suggestion.replacement.is_empty() {
+                    "remove this".into()
+                } else if range.is_empty() {
+                    format!("insert `{}`", suggestion.replacement)
+                } else {
+                    format!("replace with `{}`", suggestion.replacement)
+                };
+                let span = self.span_json(
+                    idx..idx + 1,
+                    range,
+                    true,
+                    None,
+                    Some(&suggestion.replacement),
+                );
+                children.push(child_json("help", &message, &[span]));
+            }
+            if let Some(url) = &annotation.url {
+                children.push(child_json("note", &format!("see {}", url), &[]));
+            }
+
+            let mut rendered = String::new();
+            write_events(
+                &mut rendered,
+                &stylesheet,
+                &options,
+                &self.blocks_events(&stylesheet, &options, iter::once((idx, annotation))),
+            )
+            .expect("writing to String can't fail");
+
+            let _ = writeln!(
+                json,
+                concat!(
+                    r#"{{"$message_type":"diagnostic","message":"{}","code":{},"level":"{}","#,
+                    r#""spans":[{}],"children":[{}],"rendered":"{}"}}"#,
+                ),
+                escape_json(message),
+                code,
+                rustc_level(annotation.severity),
+                spans.join(","),
+                children.join(","),
+                escape_json(&rendered),
+            );
+        }
+        json
+    }
+
+    /// Serialize span covering `range` on given lines in the format of rustc JSON
+    fn span_json(
+        &self,
+        lines: Range<usize>,
+        range: &Range<usize>,
+        is_primary: bool,
+        label: Option<&str>,
+        suggested_replacement: Option<&str>,
+    ) -> String {
+        let (first, last) = (&self.lines[lines.start], &self.lines[lines.end - 1]);
+        let text: Vec<_> = self.lines[lines.clone()]
+            .iter()
+            .map(|line| {
+                let content = line.content.strip_suffix('\n').unwrap_or(&line.content);
+                let line_end = line.start + content.len();
+                format!(
+                    r#"{{"text":"{}","highlight_start":{},"highlight_end":{}}}"#,
+                    escape_json(content),
+                    line.char_column(range.start.max(line.start)),
+                    line.char_column(range.end.min(line_end)),
+                )
+            })
+            .collect();
+        format!(
+            concat!(
+                r#"{{"file_name":"{}","byte_start":{},"byte_end":{},"line_start":{},"#,
+                r#""line_end":{},"column_start":{},"column_end":{},"is_primary":{},"#,
+                r#""text":[{}],"label":{},"suggested_replacement":{},"#,
+                r#""suggestion_applicability":{},"expansion":null}}"#,
+            ),
+            escape_json(&self.filename),
+            range.start,
+            range.end,
+            lines.start + 1,
+            lines.end,
+            first.char_column(range.start),
+            last.char_column(range.end),
+            is_primary,
+            text.join(","),
+            json_string(label),
+            json_string(suggested_replacement),
+            if suggested_replacement.is_some() {
+                r#""MaybeIncorrect""#
+            } else {
+                "null"
+            },
+        )
+    }
+}
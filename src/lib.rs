@@ -1,6 +1,7 @@
 //! This is a library to display simple errors in colorful, rustc-like way.
-//! It can't show multi-line errors or draw arrows between parts of code, but its interface
-//! is simple and easy to use. If you want something more complex, you probably should use
+//! It can show errors spanning several lines (see
+//! [`.add_multiline()`](AnnotationList::add_multiline)), but doesn't draw arrows between
+//! parts of code, and its interface is simple and easy to use. If you want something more complex, you probably should use
 //! [annotate-snippets](https://docs.rs/annotate-snippets), which is used by rustc itself.
 //!
 //! ## Basic usage
@@ -33,25 +34,85 @@
 //! ```
 
 use std::{
+    borrow::Cow,
+    cmp,
+    collections::HashMap,
     io::{self, Write},
-    iter,
     ops::Range,
 };
 use termcolor::{BufferWriter, ColorChoice, WriteColor};
 use thiserror::Error;
 
 mod annotation;
-pub use annotation::{Annotation, AnnotationText, Severity};
+pub use annotation::{
+    Annotation, AnnotationBuilder, AnnotationId, AnnotationMut, AnnotationText, Note, NoteKind,
+    ParseSeverityError, SecondarySpan, Severity, Suggestion,
+};
+
+mod span;
+pub use span::Span;
+
+mod color;
+pub use color::{default_color_choice, set_default_color_choice};
+
+mod line_index;
+pub use line_index::LineIndex;
+
+mod report;
+pub use report::{MultiFileReport, SeverityCounts};
 
 mod stylesheet;
-pub use stylesheet::Stylesheet;
+pub use stylesheet::{Labels, Stylesheet};
+
+mod options;
+pub use options::{ColumnEncoding, RenderOptions};
+
+pub mod prelude;
+
+mod checkstyle;
+
+mod github;
+
+mod html;
+pub use html::DEFAULT_HTML_CSS;
+
+mod quickfix;
+
+#[cfg(feature = "json")]
+mod json;
+
+#[cfg(feature = "json")]
+mod code_quality;
+
+#[cfg(feature = "sarif")]
+mod sarif;
+
+#[cfg(feature = "lsp")]
+pub mod lsp;
+
+#[cfg(feature = "svg")]
+mod svg;
+
+#[cfg(feature = "tui")]
+pub mod tui;
+
+#[cfg(feature = "parallel")]
+mod parallel;
+
+mod output;
+use output::{Output, Stream, StringWriter};
+
+mod render;
+use render::write_events;
+pub use render::{RenderEvent, Segment, StyleRole};
 
 #[derive(Debug, Error, PartialEq, Eq)]
 #[non_exhaustive]
 /// Errors that can occure while constructing [`AnnotationList`]. Fields of each variant are the
 /// start and the end of range, respectively.
 pub enum Error {
-    /// Provided annotation range crosses line boundary
+    /// Provided annotation range crosses line boundary. Use
+    /// [`.add_multiline()`](AnnotationList::add_multiline) for such annotations.
     #[error("range {0} .. {1} crosses line boundary")]
     MultilineRange(usize, usize),
     /// Range `end` is greater than its `start`
@@ -60,15 +121,57 @@ pub enum Error {
     /// Range starts after last line end
     #[error("range {0} .. {1} starts after last line end")]
     AfterStringEnd(usize, usize),
+    /// Range starts before first line start. Only possible with
+    /// [`AnnotationList::from_lines`]
+    #[error("range {0} .. {1} starts before first line start")]
+    BeforeStringStart(usize, usize),
+    /// Range start or end is not on a char boundary
+    #[error("range {0} .. {1} is not on char boundaries")]
+    NotCharBoundary(usize, usize),
+    /// There is no line with this 1-based number
+    #[error("there is no line {0}")]
+    NoSuchLine(usize),
+    /// Line passed to [`AnnotationList::from_lines`] starts before previous line end.
+    /// Fields are the end of previous line and the start of this one
+    #[error("line starting at {1} overlaps previous line ending at {0}")]
+    UnorderedLines(usize, usize),
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
+/// Error returned by [`AnnotationList::add`], carrying the rejected annotation back, so it
+/// can be logged or fixed and added again. Converts into [`Error`] with `?`.
+/// ```rust
+/// # use show_my_errors::{Annotation, AnnotationList, Error};
+/// # fn main() -> Result<(), Error> {
+/// let mut list = AnnotationList::new("hello.txt", "Hello world!");
+/// let rejected = list.add(Annotation::error(5..100, "too long", None)?).unwrap_err();
+/// assert_eq!(rejected.error, Error::MultilineRange(5, 100));
+/// assert_eq!(rejected.annotation.header.as_deref(), Some("too long"));
+/// assert_eq!(rejected.to_string(), "range 5 .. 100 crosses line boundary");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, PartialEq, Eq, Error)]
+#[error("{error}")]
+pub struct AddError {
+    /// Why the annotation was rejected
+    pub error: Error,
+    /// The rejected annotation
+    pub annotation: Box<Annotation>,
+}
+
+impl From<AddError> for Error {
+    fn from(err: AddError) -> Self {
+        err.error
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 #[doc(hidden)]
 pub struct AnnotatedLine<'a> {
     start: usize,
-    content: &'a str,
+    content: Cow<'a, str>,
     annotations: Vec<Annotation>,
 }
 
@@ -82,12 +185,46 @@ impl AnnotatedLine<'_> {
     }
 
     pub fn content(&self) -> &str {
-        self.content
+        &self.content
+    }
+
+    fn into_owned(self) -> AnnotatedLine<'static> {
+        AnnotatedLine {
+            start: self.start,
+            content: Cow::Owned(self.content.into_owned()),
+            annotations: self.annotations,
+        }
+    }
+
+    /// 1-based column of the offset, counted in chars. Offsets not on char boundary are
+    /// counted as if they were rounded up to one.
+    fn char_column(&self, offset: usize) -> usize {
+        self.column(offset, ColumnEncoding::Utf32)
+    }
+
+    /// 1-based column of the offset, counted in given units
+    fn column(&self, offset: usize, encoding: ColumnEncoding) -> usize {
+        encoding.column(&self.content, offset.saturating_sub(self.start)) + 1
+    }
+
+    /// 1-based column of annotation shown in the location line, counted in given units
+    /// unless overridden by [`Annotation::column`]
+    fn display_column(&self, annotation: &Annotation, encoding: ColumnEncoding) -> usize {
+        annotation
+            .column
+            .unwrap_or_else(|| self.column(annotation.range().start, encoding))
+    }
+
+    /// Annotations in the order they're displayed, i.e. sorted by priority
+    fn sorted_annotations(&self) -> Vec<&Annotation> {
+        let mut annotations: Vec<_> = self.annotations.iter().collect();
+        annotations.sort_by_key(|annotation| cmp::Reverse(annotation.priority));
+        annotations
     }
 
     pub fn add(&mut self, annotation: Annotation) -> Result<&mut Self> {
         let range = annotation.range();
-        if range.end - range.start > self.content.len() {
+        if range.end > self.start + self.content.len() {
             Err(Error::MultilineRange(range.start, range.end))
         } else {
             self.annotations.push(annotation);
@@ -102,285 +239,1403 @@ impl AnnotatedLine<'_> {
 pub struct AnnotationList<'a> {
     lines: Vec<AnnotatedLine<'a>>,
     filename: String,
+    labels: HashMap<String, String>,
+}
+
+impl AnnotationList<'static> {
+    /// Create an annotation list which owns its source, so it doesn't borrow anything and
+    /// can be stored or returned freely. This is the same as
+    /// [`AnnotationList::new`] followed by [`.into_owned()`](AnnotationList::into_owned).
+    /// ```rust
+    /// # use show_my_errors::AnnotationList;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::from_string("hello.txt", format!("Hello {}!", "world"));
+    /// list.warning(5..6, "missing comma", None)?;
+    /// assert_eq!(list.source(), "Hello world!");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_string(filename: impl AsRef<str>, string: String) -> Self {
+        AnnotationList::new(filename, &string).into_owned()
+    }
 }
 
 impl<'a> AnnotationList<'a> {
     /// Create an annotation list from string. `filename` is used only to format messages, so
     /// corresponding file doesn't need to exist.
     pub fn new(filename: impl AsRef<str>, string: &'a str) -> Self {
-        let linebreaks: Vec<_> = iter::once(0)
-            .chain(
-                string
-                    .chars()
-                    .enumerate()
-                    .filter(|(_idx, c)| *c == '\n')
-                    .map(|(idx, _c)| idx + 1),
-            )
-            .chain(iter::once(string.len()))
-            .collect();
-        let lines = linebreaks
-            .windows(2)
-            // Last line when there is a newline at the and of string
-            .filter(|bounds| bounds[0] != bounds[1])
-            .map(|bounds| AnnotatedLine {
-                start: bounds[0],
-                content: &string[bounds[0]..bounds[1]],
+        Self::with_index(filename, string, &LineIndex::new(string))
+    }
+
+    /// Create an annotation list for input that doesn't come from a file, e.g. REPL input or
+    /// generated code. Location line shows `<input>` in place of the filename; disable
+    /// [`RenderOptions::show_location`] to omit the location line entirely.
+    /// ```rust
+    /// # use show_my_errors::AnnotationList;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::anonymous("1 +");
+    /// list.error(2..3, "expected expression after `+`", None)?;
+    /// assert!(list.to_string()?.contains("--> <input>:1:3"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn anonymous(string: &'a str) -> Self {
+        Self::new("<input>", string)
+    }
+
+    /// Like [`AnnotationList::new`], but uses a prebuilt [`LineIndex`] instead of scanning
+    /// the string for newlines.
+    /// ```rust
+    /// # use show_my_errors::{AnnotationList, LineIndex};
+    /// let source = "first\nsecond\n";
+    /// let index = LineIndex::new(source);
+    /// let list = AnnotationList::with_index("file", source, &index);
+    /// assert_eq!(list, AnnotationList::new("file", source));
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if the index was built from a string of a different length. Index built from
+    /// a different string of the same length results in garbled output or panics.
+    pub fn with_index(filename: impl AsRef<str>, string: &'a str, index: &LineIndex) -> Self {
+        assert_eq!(
+            index.len(),
+            string.len(),
+            "line index was built from another string"
+        );
+        let lines = index
+            .line_bounds()
+            .map(|(start, end)| AnnotatedLine {
+                start,
+                content: Cow::Borrowed(&string[start..end]),
                 annotations: vec![],
             })
             .collect();
         Self {
             filename: filename.as_ref().into(),
             lines,
+            labels: HashMap::new(),
+        }
+    }
+
+    /// Create an annotation list from already split lines. Each item is a pair of line start
+    /// offset and line content. Line content may include trailing newline. This allows
+    /// to skip scanning for newlines if you already know line boundaries, e.g. from a lexer.
+    ///
+    /// Lines must be sorted and must not overlap, otherwise [`Error::UnorderedLines`] is
+    /// returned. Gaps between lines are allowed, but nothing can be annotated there.
+    /// ```rust
+    /// # use show_my_errors::{AnnotationList, Error};
+    /// let source = "first\nsecond";
+    /// let list = AnnotationList::from_lines("file", vec![(0, &source[..6]), (6, &source[6..])]);
+    /// assert_eq!(list.unwrap(), AnnotationList::new("file", source));
+    /// assert_eq!(
+    ///     AnnotationList::from_lines("file", vec![(6, "second"), (0, "first\n")]).unwrap_err(),
+    ///     Error::UnorderedLines(12, 0),
+    /// );
+    /// ```
+    pub fn from_lines(
+        filename: impl AsRef<str>,
+        lines: impl IntoIterator<Item = (usize, &'a str)>,
+    ) -> Result<Self> {
+        let mut prev_end = 0;
+        let lines = lines
+            .into_iter()
+            .map(|(start, content)| {
+                if start < prev_end {
+                    return Err(Error::UnorderedLines(prev_end, start));
+                }
+                prev_end = start + content.len();
+                Ok(AnnotatedLine {
+                    start,
+                    content: Cow::Borrowed(content),
+                    annotations: vec![],
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            filename: filename.as_ref().into(),
+            lines,
+            labels: HashMap::new(),
+        })
+    }
+
+    /// Copy borrowed source into the list, so it can outlive the source string, e.g. be
+    /// returned from the function which has read the file. See also
+    /// [`AnnotationList::from_string`].
+    /// ```rust
+    /// # use show_my_errors::AnnotationList;
+    /// fn check(filename: &str) -> Result<AnnotationList<'static>, show_my_errors::Error> {
+    ///     let source = String::from("let x = 1;;"); // e.g. read from `filename`
+    ///     let mut list = AnnotationList::new(filename, &source);
+    ///     list.warning(10..11, "unnecessary `;`", None)?;
+    ///     Ok(list.into_owned())
+    /// }
+    /// assert!(check("main.rs").unwrap().to_string().unwrap().contains("unnecessary `;`"));
+    /// ```
+    pub fn into_owned(self) -> AnnotationList<'static> {
+        AnnotationList {
+            lines: self
+                .lines
+                .into_iter()
+                .map(AnnotatedLine::into_owned)
+                .collect(),
+            filename: self.filename,
+            labels: self.labels,
         }
     }
 
+    /// Get the annotated text back. It's reconstructed from the lines, so for lists created
+    /// with [`AnnotationList::new`] it's equal to the original string. For lists created with
+    /// [`AnnotationList::from_lines`] it's the concatenation of the lines, without any gaps
+    /// between them.
+    pub fn source(&self) -> String {
+        self.lines.iter().map(|line| line.content()).collect()
+    }
+
+    /// Width of the widest line in chars, not counting trailing newlines. This is the width
+    /// of the widest content line [`.show()`](AnnotationList::show) can print, not counting
+    /// the gutter, if every char takes one column.
+    /// ```rust
+    /// # use show_my_errors::AnnotationList;
+    /// let list = AnnotationList::new("hello.txt", "Hello\nwörld!\n");
+    /// assert_eq!(list.max_content_width(), 6);
+    /// ```
+    pub fn max_content_width(&self) -> usize {
+        self.lines
+            .iter()
+            .map(|line| {
+                let content = line.content.strip_suffix('\n').unwrap_or(&line.content);
+                content.chars().count()
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Length of content of the line with given 1-based number in chars, not counting the
+    /// trailing newline. Returns `None` if there is no such line. This is useful to clamp
+    /// char columns, e.g. coming from an editor, before converting them to byte offsets
+    /// with [`.char_range_to_bytes()`](AnnotationList::char_range_to_bytes).
+    /// ```rust
+    /// # use show_my_errors::AnnotationList;
+    /// let list = AnnotationList::new("hello.txt", "Hello\nwörld!\n");
+    /// assert_eq!(list.line_char_len(2), Some(6));
+    /// assert_eq!(list.line_char_len(4), None);
+    /// ```
+    pub fn line_char_len(&self, line: usize) -> Option<usize> {
+        let line = self.lines.get(line.checked_sub(1)?)?;
+        let content = line.content.strip_suffix('\n').unwrap_or(&line.content);
+        Some(content.chars().count())
+    }
+
     #[doc(hidden)]
-    pub fn annotated_lines(&self) -> &[AnnotatedLine] {
+    pub fn annotated_lines(&self) -> &[AnnotatedLine<'_>] {
         &self.lines
     }
 
     /// Add an [`Annotation`] to list. You may also use [`.info()`](AnnotationList::info),
     /// [`.warning()`](AnnotationList::warning) and [`.error()`](AnnotationList::error) methods.
-    pub fn add(&mut self, annotation: Annotation) -> Result<&mut Self> {
-        let range = annotation.range();
-        let line_idx = match self
+    ///
+    /// Annotations starting at or after the end of the string are rejected with
+    /// [`Error::AfterStringEnd`]. Newline at the end of the string doesn't start a new empty
+    /// line, so to point at the end of such string use the offset of the newline itself.
+    /// The only exception is an empty string: it consists of a single empty line, and `0..0`
+    /// annotations can be added to it.
+    ///
+    /// Zero-length annotations are shown without carets, except for the ones at the end of a
+    /// non-empty line (i.e. at its newline), which get a caret just past the line content.
+    ///
+    /// [Secondary spans](Annotation::add_secondary) and
+    /// [suggestions](Annotation::add_suggestion) are checked the same way.
+    ///
+    /// Rejected annotation is returned back in [`AddError`].
+    pub fn add(&mut self, annotation: Annotation) -> Result<&mut Self, AddError> {
+        let range = annotation.range().clone();
+        let error = match self.line_index_for(range.start) {
+            Ok(idx) => {
+                let line = &self.lines[idx];
+                if range.end > line.start + line.content.len() {
+                    Error::MultilineRange(range.start, range.end)
+                } else if let Err(error) = self.check_related_spans(&annotation) {
+                    error
+                } else {
+                    self.lines[idx].annotations.push(annotation);
+                    return Ok(self);
+                }
+            }
+            Err(Error::BeforeStringStart(..)) => Error::BeforeStringStart(range.start, range.end),
+            Err(_) => Error::AfterStringEnd(range.start, range.end),
+        };
+        Err(AddError {
+            error,
+            annotation: Box::new(annotation),
+        })
+    }
+
+    /// Like [`.add()`](AnnotationList::add), but annotation may span several lines, e.g. an
+    /// unterminated string literal. Such annotations are shown with all lines they cover,
+    /// with the start and the end of the span connected along the gutter:
+    /// ```rust
+    /// # use show_my_errors::{Annotation, AnnotationList};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("main.rs", "let s = \"abc\ndef\nghi;\n");
+    /// list.add_multiline(Annotation::error(8..21, "unterminated string", "starts here")?)?;
+    /// assert_eq!(
+    ///     list.to_string()?,
+    ///     r#"error: unterminated string
+    ///   --> main.rs:1:9
+    ///    |
+    ///  1 |   let s = "abc
+    ///    |  _________^
+    ///  2 | | def
+    ///  3 | | ghi;
+    ///    | |____^ starts here
+    /// "#
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Styling of annotated fragment ([`Stylesheet::highlight_span`],
+    /// [`RenderOptions::underline_span`] and others) applies only to single-line annotations.
+    pub fn add_multiline(&mut self, annotation: Annotation) -> Result<&mut Self, AddError> {
+        let range = annotation.range().clone();
+        let source_end = self
             .lines
-            .binary_search_by(|line| line.start.cmp(&range.start))
-        {
+            .last()
+            .map_or(0, |line| line.start + line.content.len());
+        let error = match self.line_index_for(range.start) {
+            Ok(_) if range.end > source_end => Error::AfterStringEnd(range.start, range.end),
+            Ok(idx) => match self.check_related_spans(&annotation) {
+                Err(error) => error,
+                Ok(()) => {
+                    self.lines[idx].annotations.push(annotation);
+                    return Ok(self);
+                }
+            },
+            Err(Error::BeforeStringStart(..)) => Error::BeforeStringStart(range.start, range.end),
+            Err(_) => Error::AfterStringEnd(range.start, range.end),
+        };
+        Err(AddError {
+            error,
+            annotation: Box::new(annotation),
+        })
+    }
+
+    /// Like [`.add()`](AnnotationList::add), but returns a handle of the added annotation,
+    /// which can be used to access it later.
+    /// ```rust
+    /// # use show_my_errors::{Annotation, AnnotationList};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("hello.txt", "Hello world!");
+    /// let id = list.push(Annotation::error(6..11, "unknown planet", None)?)?;
+    /// list.annotation_mut(id)
+    ///     .unwrap()
+    ///     .set_text("did you mean `Earth`?");
+    /// assert!(list.to_string()?.contains("^^^^^ did you mean `Earth`?"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn push(&mut self, annotation: Annotation) -> Result<AnnotationId, AddError> {
+        self.push_with(annotation, Self::add)
+    }
+
+    /// Like [`.add_multiline()`](AnnotationList::add_multiline), but returns a handle of the
+    /// added annotation, see [`.push()`](AnnotationList::push)
+    pub fn push_multiline(&mut self, annotation: Annotation) -> Result<AnnotationId, AddError> {
+        self.push_with(annotation, Self::add_multiline)
+    }
+
+    fn push_with(
+        &mut self,
+        annotation: Annotation,
+        add: fn(&mut Self, Annotation) -> Result<&mut Self, AddError>,
+    ) -> Result<AnnotationId, AddError> {
+        let line = self
+            .line_index_for(annotation.range().start)
+            .unwrap_or_default();
+        let index = self
+            .lines
+            .get(line)
+            .map_or(0, |line| line.annotations.len());
+        add(self, annotation)?;
+        Ok(AnnotationId { line, index })
+    }
+
+    /// Get annotation by its handle
+    pub fn annotation(&self, id: AnnotationId) -> Option<&Annotation> {
+        self.lines.get(id.line)?.annotations.get(id.index)
+    }
+
+    /// Get mutable access to annotation by its handle
+    pub fn annotation_mut(&mut self, id: AnnotationId) -> Option<AnnotationMut<'_>> {
+        let annotation = self.lines.get(id.line)?.annotations.get(id.index)?;
+        let last = &self.lines[self.end_line_index(id.line, annotation)];
+        let end = last.start + last.content.len();
+        let line = &mut self.lines[id.line];
+        Some(AnnotationMut {
+            line: line.start..line.start + line.content.len(),
+            end,
+            annotation: &mut line.annotations[id.index],
+        })
+    }
+
+    /// Find index of the line containing `offset`. Returns [`Error::BeforeStringStart`] or
+    /// [`Error::AfterStringEnd`] with `offset .. offset` range if there is no such line.
+    fn line_index_for(&self, offset: usize) -> Result<usize> {
+        let idx = match self.lines.binary_search_by(|line| line.start.cmp(&offset)) {
             Ok(idx) => idx,
             Err(idx) if idx > 0 => idx - 1,
-            _ => unreachable!("lines in AnnotationList not starting at 0"),
+            _ => return Err(Error::BeforeStringStart(offset, offset)),
         };
-        let line = &mut self.lines[line_idx];
-        if range.start >= line.start() + line.content.len() {
-            Err(Error::AfterStringEnd(range.start, range.end))
+        let line = &self.lines[idx];
+        let end = line.start + line.content.len();
+        // Empty line without newline can only be the only line of an empty string
+        if offset > end || (offset == end && !line.content.is_empty()) {
+            Err(Error::AfterStringEnd(offset, offset))
         } else {
-            self.lines[line_idx].add(annotation)?;
-            Ok(self)
+            Ok(idx)
+        }
+    }
+
+    /// Check that secondary spans and suggestions of the annotation lie on single lines of
+    /// the source
+    fn check_related_spans(&self, annotation: &Annotation) -> Result<()> {
+        let secondary = annotation
+            .secondary_spans()
+            .iter()
+            .map(SecondarySpan::range);
+        let suggestions = annotation.suggestions().iter().map(Suggestion::range);
+        for range in secondary.chain(suggestions) {
+            let idx = self
+                .line_index_for(range.start)
+                .map_err(|error| match error {
+                    Error::BeforeStringStart(..) => {
+                        Error::BeforeStringStart(range.start, range.end)
+                    }
+                    _ => Error::AfterStringEnd(range.start, range.end),
+                })?;
+            let line = &self.lines[idx];
+            if range.end > line.start + line.content.len() {
+                return Err(Error::MultilineRange(range.start, range.end));
+            }
+        }
+        Ok(())
+    }
+
+    /// Find index of the last line covered by annotation starting on line `idx`, i.e. the line
+    /// containing its last byte. This is `idx` for all annotations except multi-line ones.
+    /// Ranges past the end of the source can't be added, but if they are somehow there, they
+    /// are shown on their start line.
+    fn end_line_index(&self, idx: usize, annotation: &Annotation) -> usize {
+        let range = annotation.range();
+        let line = &self.lines[idx];
+        let source_end = self
+            .lines
+            .last()
+            .map_or(0, |line| line.start + line.content.len());
+        if range.end <= line.start + line.content.len() || range.end > source_end {
+            idx
+        } else {
+            self.lines.partition_point(|line| line.start < range.end) - 1
+        }
+    }
+
+    /// Like [`.add()`](AnnotationList::add), but annotation range is interpreted as a range
+    /// of char indices instead of byte offsets. See
+    /// [`.char_range_to_bytes()`](AnnotationList::char_range_to_bytes) for details.
+    /// ```rust
+    /// # use show_my_errors::{Annotation, AnnotationList};
+    /// # fn main() -> Result<(), show_my_errors::Error> {
+    /// let mut list = AnnotationList::new("hello.txt", "Héllo wörld!");
+    /// list.add_chars(Annotation::info(6..11, "header", None)?)?;
+    /// assert_eq!(list.annotations().next().unwrap().range(), &(7..13));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_chars(&mut self, mut annotation: Annotation) -> Result<&mut Self, AddError> {
+        let range = self.char_range_to_bytes(annotation.range().clone());
+        match range.and_then(|range| annotation.set_range(range)) {
+            Ok(()) => self.add(annotation),
+            Err(error) => Err(AddError {
+                error,
+                annotation: Box::new(annotation),
+            }),
+        }
+    }
+
+    /// Convert a range of char indices to a range of byte offsets. Chars are counted from the
+    /// start of the first line. Returns [`Error::AfterStringEnd`] with the original range if
+    /// it doesn't fit into the text, or [`Error::InvalidRange`] if it has `start > end`.
+    pub fn char_range_to_bytes(&self, range: impl Into<Span>) -> Result<Range<usize>> {
+        let range: Range<usize> = range.into().into();
+        if range.end < range.start {
+            return Err(Error::InvalidRange(range.start, range.end));
+        }
+        let mut offsets = self
+            .lines
+            .iter()
+            .flat_map(|line| {
+                line.content
+                    .char_indices()
+                    .map(move |(idx, _c)| line.start + idx)
+            })
+            .chain(
+                self.lines
+                    .last()
+                    .map(|line| line.start + line.content.len()),
+            );
+        let start = offsets.nth(range.start);
+        let end = match range.end - range.start {
+            0 => start,
+            len => offsets.nth(len - 1),
+        };
+        match (start, end) {
+            (Some(start), Some(end)) => Ok(start..end),
+            _ => Err(Error::AfterStringEnd(range.start, range.end)),
         }
     }
 
+    /// Define a label which can be shared by several annotations via
+    /// [`Annotation::label_id`], so they show the same text. All annotations except the first
+    /// one using the label refer back to its position.
+    /// ```rust
+    /// # use show_my_errors::{Annotation, AnnotationList};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("hello.rs", "let x = 1;\nlet x = 2;");
+    /// list.define_label("first", "first definition of `x`");
+    /// for range in [4..5, 15..16] {
+    ///     list.add(Annotation::builder(range).header("shadowing").label_id("first").build()?)?;
+    /// }
+    /// let output = list.to_string()?;
+    /// assert!(output.contains("^ first definition of `x`\n"));
+    /// assert!(output.contains("^ first definition of `x` (see 1:5)\n"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn define_label(&mut self, id: impl Into<String>, text: impl Into<String>) -> &mut Self {
+        self.labels.insert(id.into(), text.into());
+        self
+    }
+
+    /// Add a [`Severity::Help`] annotation to list. See [`Annotation::new`] docs for details
+    pub fn help(
+        &mut self,
+        range: impl Into<Span>,
+        header: impl AnnotationText,
+        text: impl AnnotationText,
+    ) -> Result<&mut Self> {
+        Ok(self.add(Annotation::help(range, header, text)?)?)
+    }
+
+    /// Add a [`Severity::Note`] annotation to list. See [`Annotation::new`] docs for details
+    pub fn note(
+        &mut self,
+        range: impl Into<Span>,
+        header: impl AnnotationText,
+        text: impl AnnotationText,
+    ) -> Result<&mut Self> {
+        Ok(self.add(Annotation::note(range, header, text)?)?)
+    }
+
     /// Add an [`Severity::Info`] annotation to list. See [`Annotation::new`] docs for details
     pub fn info(
         &mut self,
-        range: Range<usize>,
+        range: impl Into<Span>,
         header: impl AnnotationText,
         text: impl AnnotationText,
     ) -> Result<&mut Self> {
-        self.add(Annotation::info(range, header, text)?)
+        Ok(self.add(Annotation::info(range, header, text)?)?)
     }
 
     /// Add an [`Severity::Warning`] annotation to list. See [`Annotation::new`] docs for details
     pub fn warning(
         &mut self,
-        range: Range<usize>,
+        range: impl Into<Span>,
         header: impl AnnotationText,
         text: impl AnnotationText,
     ) -> Result<&mut Self> {
-        self.add(Annotation::warning(range, header, text)?)
+        Ok(self.add(Annotation::warning(range, header, text)?)?)
     }
 
     /// Add an [`Severity::Error`] annotation to list. See [`Annotation::new`] docs for details
     pub fn error(
         &mut self,
-        range: Range<usize>,
+        range: impl Into<Span>,
         header: impl AnnotationText,
         text: impl AnnotationText,
     ) -> Result<&mut Self> {
-        self.add(Annotation::error(range, header, text)?)
+        Ok(self.add(Annotation::error(range, header, text)?)?)
     }
 
-    /// Print an error message to stream using given stylesheet. If your stream implements
-    /// [`Write`](std::io::Write), but not [`WriteColor`](termcolor::WriteColor), consider wrapping
-    /// it into [`termcolor::Ansi`] or [`termcolor::NoColor`].
-    ///
-    /// This method uses no buffering, so you probably want to pass [`termcolor::Buffer`] to it
-    /// rather than raw stream.
-    ///
-    /// If you want to just print message to stdout/stderr, consider using
-    /// [`.print_stdout()`](AnnotationList::show_stdout) or
-    /// [`.print_stderr()`](AnnotationList::show_stderr) instead.
-    pub fn show<W: Write + WriteColor>(
-        &self,
-        mut stream: W,
-        stylesheet: &Stylesheet,
-    ) -> io::Result<()> {
-        let mut first_output = true;
-        for (idx, line) in self.lines.iter().enumerate() {
-            for annotation in line.annotations() {
-                let range = annotation.range();
-
-                // Padding
-                if first_output {
-                    first_output = false;
-                } else {
-                    stream.write(b"\n")?;
-                }
-
-                // Severity and header
-                let severity_color = stylesheet.by_severity(&annotation.severity);
-                stream.set_color(severity_color)?;
-                write!(stream, "{}:", annotation.severity)?;
-                if let Some(header) = &annotation.header {
-                    write!(stream, " {}\n", header)?;
-                } else {
-                    stream.write(b"\n")?;
-                }
+    /// Attach a message to the whole line with given 1-based number. Unlike a zero-length
+    /// annotation, it's shown without carets and without column in the location line, and its
+    /// text is shown right after the gutter. Returns [`Error::NoSuchLine`] if there is no such
+    /// line.
+    /// ```rust
+    /// # use show_my_errors::{AnnotationList, Severity};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("hello.txt", "Hello world!");
+    /// list.line_note(1, Severity::Info, "greeting", "this line is fine")?;
+    /// assert_eq!(list.to_string()?, r#"info: greeting
+    ///   --> hello.txt:1
+    ///    |
+    ///  1 | Hello world!
+    ///    | this line is fine
+    /// "#);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn line_note(
+        &mut self,
+        line: usize,
+        severity: Severity,
+        header: impl AnnotationText,
+        text: impl AnnotationText,
+    ) -> Result<&mut Self> {
+        let start = line
+            .checked_sub(1)
+            .and_then(|idx| self.lines.get(idx))
+            .ok_or(Error::NoSuchLine(line))?
+            .start;
+        let mut annotation = Annotation::new(start..start, severity, header, text)?;
+        annotation.line_note = true;
+        Ok(self.add(annotation)?)
+    }
 
-                // Line numbers column & filename
-                stream.set_color(&stylesheet.linenr)?;
-                let linenr = (idx + 1).to_string();
-                let nrcol_width = linenr.len() + 2;
-                print_n(&mut stream, b" ", linenr.len() + 1)?;
-                write!(stream, "--> ")?;
-                stream.set_color(&stylesheet.filename)?;
-                write!(
-                    stream,
-                    "{}:{}:{}\n",
-                    self.filename,
-                    idx + 1,
-                    range.start - line.start() + 1
-                )?;
-                stream.set_color(&stylesheet.linenr)?;
-                print_n(&mut stream, b" ", nrcol_width)?;
-                write!(stream, "|\n {} | ", idx + 1)?;
-
-                // Line content
-                stream.set_color(&stylesheet.content)?;
-                write!(stream, "{}", line.content)?;
-                if !line.content.ends_with('\n') {
-                    stream.write(b"\n")?;
-                }
+    /// Add a [`Severity::Info`] annotation proposing to replace the fragment with `new_text`.
+    /// The replacement is shown under the carets, aligned with the replaced fragment.
+    /// ```rust
+    /// # use show_my_errors::AnnotationList;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("hello.rs", "let x = fo;");
+    /// list.replacement(8..10, "unknown variable", "foo")?;
+    /// assert_eq!(list.to_string()?, r#"info: unknown variable
+    ///   --> hello.rs:1:9
+    ///    |
+    ///  1 | let x = fo;
+    ///    |         ^^ help: replace with
+    ///    |         foo
+    /// "#);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn replacement(
+        &mut self,
+        range: impl Into<Span>,
+        header: impl AnnotationText,
+        new_text: impl Into<String>,
+    ) -> Result<&mut Self> {
+        let mut annotation = Annotation::info(range, header, "help: replace with")?;
+        annotation.replacement = Some(new_text.into());
+        Ok(self.add(annotation)?)
+    }
 
-                // Line numbers column
-                stream.set_color(&stylesheet.linenr)?;
-                print_n(&mut stream, b" ", nrcol_width)?;
-                stream.write(b"|")?;
-
-                // Annotation
-                if range.end - range.start != 0 {
-                    stream.set_color(severity_color)?;
-                    print_n(&mut stream, b" ", range.start - line.start + 1)?;
-                    print_n(&mut stream, b"^", range.end - range.start)?;
-                    if let Some(text) = &annotation.text {
-                        write!(stream, " {}", text)?;
-                    }
-                }
-                stream.write(b"\n")?;
-                stream.reset()?;
+    /// Add an annotation for each of the given spans, e.g. regex matches. Spans crossing line
+    /// boundary are skipped. On any other error adding stops; annotations added before the
+    /// failure remain in the list.
+    ///
+    /// This takes byte ranges of matches rather than a `regex::Regex`, so it works with any
+    /// matcher (`Regex::find_iter`, [`str::match_indices`], a hand-written scanner) without
+    /// making this crate depend on `regex`.
+    /// ```rust
+    /// # use show_my_errors::{AnnotationList, Severity};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let source = "let foo = 1;\nlet bar = foo;";
+    /// let mut list = AnnotationList::new("main.rs", source);
+    /// // With regex crate, this would be `re.find_iter(source).map(|m| m.range())`
+    /// let matches = source.match_indices("foo").map(|(idx, m)| idx..idx + m.len());
+    /// list.annotate_matches(matches, Severity::Warning, "bad name", "rename this")?;
+    /// assert_eq!(list.annotations().count(), 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn annotate_matches(
+        &mut self,
+        matches: impl IntoIterator<Item = Range<usize>>,
+        severity: Severity,
+        header: impl AnnotationText,
+        text: impl AnnotationText,
+    ) -> Result<&mut Self> {
+        let header = header.into_option_string();
+        let text = text.into_option_string();
+        for range in matches {
+            let annotation = Annotation::new(range, severity, header.clone(), text.clone())?;
+            match self.add(annotation) {
+                Ok(_)
+                | Err(AddError {
+                    error: Error::MultilineRange(..),
+                    ..
+                }) => {}
+                Err(err) => return Err(err.into()),
             }
         }
-        Ok(())
+        Ok(self)
     }
 
-    fn show_bufwriter(&self, stream: BufferWriter, stylesheet: &Stylesheet) -> io::Result<()> {
-        let mut buf = stream.buffer();
-        self.show(&mut buf, stylesheet)?;
-        stream.print(&buf)
+    /// Add annotations from `(range, severity, header)` tuples. Returns number of added
+    /// annotations. Adding stops at the first error, which is returned along with the number
+    /// of annotations added before it. These annotations remain in the list.
+    /// ```rust
+    /// # use show_my_errors::{AnnotationList, Error, Severity};
+    /// let mut list = AnnotationList::new("hello.txt", "Hello world!");
+    /// let result = list.extend(vec![
+    ///     (0..5, Severity::Info, "greeting".to_string()),
+    ///     (100..105, Severity::Error, "out of range".to_string()),
+    ///     (6..11, Severity::Info, "never added".to_string()),
+    /// ]);
+    /// assert_eq!(result, Err((1, Error::AfterStringEnd(100, 105))));
+    /// ```
+    pub fn extend(
+        &mut self,
+        iter: impl IntoIterator<Item = (Range<usize>, Severity, String)>,
+    ) -> Result<usize, (usize, Error)> {
+        let mut added = 0;
+        for (range, severity, header) in iter {
+            Annotation::new(range, severity, header, None)
+                .and_then(|annotation| Ok(self.add(annotation)?))
+                .map_err(|err| (added, err))?;
+            added += 1;
+        }
+        Ok(added)
     }
 
-    /// Print error message to stdout. Output will be colorized if stdout is a TTY
-    pub fn show_stdout(&self, stylesheet: &Stylesheet) -> io::Result<()> {
-        let color_choice = if atty::is(atty::Stream::Stdout) {
-            ColorChoice::Auto
-        } else {
-            ColorChoice::Never
-        };
-        self.show_bufwriter(termcolor::BufferWriter::stdout(color_choice), stylesheet)
+    /// Iterate over all annotations in the order they're displayed
+    pub fn annotations(&self) -> impl Iterator<Item = &Annotation> {
+        self.blocks().map(|(_idx, annotation)| annotation)
     }
 
-    /// Print error message to stderr. Output will be colorized if stderr is a TTY
-    pub fn show_stderr(&self, stylesheet: &Stylesheet) -> io::Result<()> {
-        let color_choice = if atty::is(atty::Stream::Stderr) {
-            ColorChoice::Auto
-        } else {
-            ColorChoice::Never
-        };
-        self.show_bufwriter(termcolor::BufferWriter::stderr(color_choice), stylesheet)
+    /// Iterate over all annotations in the order they're displayed, along with 1-based line
+    /// and column shown for them in the location line.
+    /// ```rust
+    /// # use show_my_errors::AnnotationList;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("hello.txt", "Hello\nworld!");
+    /// list.warning(8..10, "header", None)?;
+    /// let (line, column, _annotation) = list.located_annotations().next().unwrap();
+    /// assert_eq!((line, column), (2, 3));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn located_annotations(&self) -> impl Iterator<Item = (usize, usize, &Annotation)> {
+        self.located_annotations_with_options(&RenderOptions::default())
     }
 
-    /// "Print" monochrome message to `Vec<u8>`
-    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
-        let mut buf = termcolor::Buffer::no_color();
-        self.show(&mut buf, &Stylesheet::monochrome())?;
-        Ok(buf.into_inner())
+    /// Like [`.located_annotations()`](AnnotationList::located_annotations), but columns are
+    /// the ones shown with given layout settings, i.e. counted in
+    /// [`RenderOptions::column_encoding`] units
+    pub fn located_annotations_with_options(
+        &self,
+        options: &RenderOptions,
+    ) -> impl Iterator<Item = (usize, usize, &Annotation)> {
+        let encoding = options.column_encoding;
+        self.blocks().map(move |(idx, annotation)| {
+            (
+                idx + 1,
+                self.lines[idx].display_column(annotation, encoding),
+                annotation,
+            )
+        })
     }
 
-    /// "Print" message to `Vec<u8>`, colorizing it using ANSI escape codes
-    pub fn to_ansi_bytes(&self, stylesheet: &Stylesheet) -> io::Result<Vec<u8>> {
-        let mut buf = termcolor::Buffer::ansi();
-        self.show(&mut buf, stylesheet)?;
-        Ok(buf.into_inner())
+    /// Get the most severe severity among all annotations, or `None` if there are none
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.annotations()
+            .map(|annotation| annotation.severity)
+            .max()
     }
 
-    /// "Print" monochrome message to [`String`]
-    /// # Panics
-    /// Panics if message cannot be converted to UTF-8
-    pub fn to_string(&self) -> io::Result<String> {
-        Ok(String::from_utf8(self.to_bytes()?).expect("invalid utf-8 in AnnotationList"))
+    /// Count annotations by severity, see [`SeverityCounts::summary`]
+    pub fn severity_counts(&self) -> SeverityCounts {
+        let mut counts = SeverityCounts::default();
+        counts.count(self.annotations());
+        counts
     }
 
-    /// "Print" message to [`String`], colorizing it using ANSI escape codes
-    /// # Panics
-    /// Panics if message cannot be converted to UTF-8
-    pub fn to_ansi_string(&self, stylesheet: &Stylesheet) -> io::Result<String> {
-        Ok(String::from_utf8(self.to_ansi_bytes(stylesheet)?)
-            .expect("invalid utf-8 in AnnotationList"))
+    /// Exit code a command-line tool should use after showing this list: `1` if there are
+    /// any errors or custom severities at least as severe as [`Severity::Error`], `0` otherwise.
+    /// Warnings and less severe annotations don't fail the run.
+    /// ```rust
+    /// # use show_my_errors::AnnotationList;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("hello.txt", "Hello world!");
+    /// list.warning(4..7, "punctuation problem", "you probably forgot a comma")?;
+    /// assert_eq!(list.suggested_exit_code(), 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn suggested_exit_code(&self) -> i32 {
+        match self.max_severity() {
+            Some(severity) if severity.rank() >= Severity::Error.rank() => 1,
+            _ => 0,
+        }
     }
-}
 
-fn print_n(mut stream: impl io::Write, buf: &[u8], count: usize) -> io::Result<()> {
-    for _ in 0..count {
-        stream.write(buf)?;
+    /// Turn all warnings into errors, e.g. to treat warnings as failures in CI. Infos are left
+    /// as is. Returns the number of promoted annotations.
+    /// ```rust
+    /// # use show_my_errors::AnnotationList;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("hello.txt", "Hello world!");
+    /// list.warning(4..7, "punctuation problem", "you probably forgot a comma")?;
+    /// list.promote_warnings_to_errors();
+    /// assert_eq!(list.suggested_exit_code(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn promote_warnings_to_errors(&mut self) -> usize {
+        let mut promoted = 0;
+        for annotation in self.lines.iter_mut().flat_map(|line| &mut line.annotations) {
+            if annotation.severity == Severity::Warning {
+                annotation.severity = Severity::Error;
+                promoted += 1;
+            }
+        }
+        promoted
     }
-    Ok(())
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
-    fn assert_start_content<'a>(line: &AnnotatedLine<'a>, start: usize, content: &'a str) {
-        assert_eq!(line.start(), start);
-        assert_eq!(line.content(), content);
+    /// Get annotations on the line with given 1-based number. Returns an empty slice if there
+    /// is no such line.
+    pub fn annotations_on_line(&self, line: usize) -> &[Annotation] {
+        line.checked_sub(1)
+            .and_then(|idx| self.lines.get(idx))
+            .map_or(&[], |line| line.annotations())
     }
 
-    fn create_list() -> AnnotationList<'static> {
-        AnnotationList::new("test.txt", "\nstring\nwith\nmany\n\nnewlines\n\n")
+    /// Remove annotations which have the [same location](Annotation::same_location) as some
+    /// annotation added before them, and return the number of removed annotations. This
+    /// invalidates [`AnnotationId`]s.
+    pub fn dedup_locations(&mut self) -> usize {
+        let mut removed = 0;
+        for line in &mut self.lines {
+            let mut kept: Vec<Annotation> = Vec::with_capacity(line.annotations.len());
+            for annotation in line.annotations.drain(..) {
+                if kept.iter().any(|other| other.same_location(&annotation)) {
+                    removed += 1;
+                } else {
+                    kept.push(annotation);
+                }
+            }
+            line.annotations = kept;
+        }
+        removed
     }
 
-    #[test]
-    fn test_new_many_newlines() {
-        let annotation_list = create_list();
-        let mut lines = annotation_list.annotated_lines().iter();
-        assert_start_content(lines.next().unwrap(), 0, "\n");
-        assert_start_content(lines.next().unwrap(), 1, "string\n");
-        assert_start_content(lines.next().unwrap(), 8, "with\n");
-        assert_start_content(lines.next().unwrap(), 13, "many\n");
-        assert_start_content(lines.next().unwrap(), 18, "\n");
-        assert_start_content(lines.next().unwrap(), 19, "newlines\n");
-        assert_start_content(lines.next().unwrap(), 28, "\n");
-        assert!(lines.next().is_none());
+    /// Check that all annotations would still be valid if they were added to a list created
+    /// from `new_source`, e.g. after the source was edited. Returns the error for the first
+    /// annotation which doesn't fit into a single line of `new_source`, or
+    /// [`Error::NotCharBoundary`] if its range splits a char.
+    /// ```rust
+    /// # use show_my_errors::{AnnotationList, Error};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("hello.txt", "Hello world!");
+    /// list.warning(6..11, "header", None)?;
+    /// assert_eq!(list.revalidate("Hello world"), Ok(()));
+    /// assert_eq!(list.revalidate("Hello"), Err(Error::AfterStringEnd(6, 11)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn revalidate(&self, new_source: &str) -> Result<()> {
+        let mut list = AnnotationList::new(&self.filename, new_source);
+        for (idx, annotation) in self.blocks() {
+            if self.end_line_index(idx, annotation) == idx {
+                list.add(annotation.clone())?;
+            } else {
+                list.add_multiline(annotation.clone())?;
+            }
+            let range = annotation.range();
+            if !new_source.is_char_boundary(range.start) || !new_source.is_char_boundary(range.end)
+            {
+                return Err(Error::NotCharBoundary(range.start, range.end));
+            }
+        }
+        Ok(())
     }
 
-    #[test]
-    fn test_new_without_newlines() {
-        let annotation_list = AnnotationList::new("filename", "string without newlines");
-        let mut lines = annotation_list.annotated_lines().iter();
-        assert_start_content(lines.next().unwrap(), 0, "string without newlines");
-        assert!(lines.next().is_none());
+    /// Find pairs of annotations with overlapping ranges. Carets of such annotations collide
+    /// in the output, so you may want to check this before rendering.
+    ///
+    /// Annotations are numbered in the order they're displayed, i.e. by line, then by
+    /// priority and then by insertion order. Each pair is reported once, with the smaller index first.
+    pub fn find_overlaps(&self) -> Vec<(usize, usize)> {
+        let mut overlaps = vec![];
+        let mut offset = 0;
+        for line in &self.lines {
+            let annotations = line.sorted_annotations();
+            for (i, first) in annotations.iter().enumerate() {
+                for (j, second) in annotations.iter().enumerate().skip(i + 1) {
+                    if first.overlaps(second) {
+                        overlaps.push((offset + i, offset + j));
+                    }
+                }
+            }
+            offset += annotations.len();
+        }
+        overlaps
     }
 
-    #[test]
-    fn test_new_trailing_newline() {
-        let annotation_list = AnnotationList::new("filename", "string with trailing newline\n");
-        let mut lines = annotation_list.annotated_lines().iter();
-        assert_start_content(lines.next().unwrap(), 0, "string with trailing newline\n");
+    /// Get the source with [suggestions](Annotation::add_suggestion) of all annotations
+    /// applied, e.g. to implement a `--fix` option. Suggestions are applied in source order;
+    /// ones overlapping a previously applied suggestion are skipped.
+    /// ```rust
+    /// # use show_my_errors::{Annotation, AnnotationList};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("main.rs", "let x = foo(1 2)\n");
+    /// let mut comma = Annotation::error(13..14, "expected `,`", None)?;
+    /// comma.add_suggestion(13..13, ",")?;
+    /// let mut semicolon = Annotation::error(15..16, "expected `;`", None)?;
+    /// semicolon.add_suggestion(16..16, ";")?;
+    /// let mut unused = Annotation::warning(4..5, "unused variable", None)?;
+    /// unused.add_suggestion(4..5, "_x")?;
+    /// list.add(comma)?.add(semicolon)?.add(unused)?;
+    /// assert_eq!(list.apply_suggestions(), "let _x = foo(1, 2);\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn apply_suggestions(&self) -> String {
+        let source = self.source();
+        let mut suggestions: Vec<_> = self
+            .annotations()
+            .flat_map(Annotation::suggestions)
+            .collect();
+        suggestions.sort_by_key(|suggestion| (suggestion.range().start, suggestion.range().end));
+        let mut fixed = String::with_capacity(source.len());
+        let mut pos = 0;
+        for suggestion in suggestions {
+            let range = suggestion.range();
+            if range.start < pos || source.get(range.clone()).is_none() {
+                continue;
+            }
+            fixed.push_str(&source[pos..range.start]);
+            fixed.push_str(&suggestion.replacement);
+            pos = range.end;
+        }
+        fixed.push_str(&source[pos..]);
+        fixed
     }
 
-    #[test]
-    fn test_new_leading_newline() {
-        let annotation_list = AnnotationList::new("filename", "\nstring with leading newline");
+    /// Print an error message to stream using given stylesheet. If your stream implements
+    /// [`Write`](std::io::Write), but not [`WriteColor`](termcolor::WriteColor), consider wrapping
+    /// it into [`termcolor::Ansi`] or [`termcolor::NoColor`].
+    ///
+    /// This method uses no buffering, so you probably want to pass [`termcolor::Buffer`] to it
+    /// rather than raw stream.
+    ///
+    /// This method doesn't detect terminal capabilities itself: colors are always set through
+    /// [`WriteColor`], so output is colored exactly when the stream
+    /// supports color. E.g. [`termcolor::StandardStream`] created with [`ColorChoice::Auto`]
+    /// decides it on its own.
+    /// ```rust
+    /// # use show_my_errors::{AnnotationList, Stylesheet};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("hello.txt", "Hello world!");
+    /// list.error(0..5, "header", None)?;
+    /// let mut colored = termcolor::Ansi::new(vec![]);
+    /// list.show(&mut colored, &Stylesheet::colored())?;
+    /// assert!(colored.into_inner().contains(&b'\x1b'));
+    /// let mut plain = termcolor::NoColor::new(vec![]);
+    /// list.show(&mut plain, &Stylesheet::colored())?;
+    /// assert!(!plain.into_inner().contains(&b'\x1b'));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// If you want to just print message to stdout/stderr, consider using
+    /// [`.print_stdout()`](AnnotationList::show_stdout) or
+    /// [`.print_stderr()`](AnnotationList::show_stderr) instead.
+    pub fn show<W: Write + WriteColor>(
+        &self,
+        stream: W,
+        stylesheet: &Stylesheet,
+    ) -> io::Result<()> {
+        self.show_with_options(stream, stylesheet, &RenderOptions::default())
+    }
+
+    /// Like [`.show()`](AnnotationList::show), but uses given layout settings
+    pub fn show_with_options<W: Write + WriteColor>(
+        &self,
+        stream: W,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        write_events(
+            &mut Stream(stream),
+            stylesheet,
+            options,
+            &self.render_events_with_options(stylesheet, options),
+        )
+    }
+
+    /// Like [`.show()`](AnnotationList::show), but annotations are grouped by severity: all
+    /// errors go first, then warnings, infos, notes and helps. Annotations of the same severity are sorted
+    /// by their position in source.
+    ///
+    /// Texts of annotations on the same line are aligned to a single column, one space after
+    /// the rightmost caret among them, see [`RenderOptions::align_labels`].
+    pub fn show_grouped<W: Write + WriteColor>(
+        &self,
+        stream: W,
+        stylesheet: &Stylesheet,
+    ) -> io::Result<()> {
+        self.show_grouped_with_options(stream, stylesheet, &RenderOptions::default())
+    }
+
+    /// Like [`.show_grouped()`](AnnotationList::show_grouped), but uses given layout
+    /// settings. Labels are aligned regardless of [`RenderOptions::align_labels`].
+    pub fn show_grouped_with_options<W: Write + WriteColor>(
+        &self,
+        stream: W,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        let options = RenderOptions {
+            align_labels: true,
+            ..options.clone()
+        };
+        let mut blocks: Vec<_> = self.blocks().collect();
+        blocks.sort_by_key(|(idx, annotation)| {
+            (
+                cmp::Reverse(annotation.severity),
+                *idx,
+                cmp::Reverse(annotation.priority),
+                annotation.range().start,
+            )
+        });
+        write_events(
+            &mut Stream(stream),
+            stylesheet,
+            &options,
+            &self.blocks_events(stylesheet, &options, blocks),
+        )
+    }
+
+    /// Like [`.show()`](AnnotationList::show), but only annotations with severity of at least
+    /// `min_severity` are shown, comparing their [ranks](Severity::rank). If there are no such
+    /// annotations, nothing is written.
+    /// ```rust
+    /// # use show_my_errors::{AnnotationList, Severity, Stylesheet};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("hello.txt", "Hello world!");
+    /// list.info(0..5, "greeting", None)?;
+    /// let mut buf = termcolor::Buffer::no_color();
+    /// list.show_filtered(&mut buf, Severity::Warning, &Stylesheet::monochrome())?;
+    /// assert!(buf.is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn show_filtered<W: Write + WriteColor>(
+        &self,
+        stream: W,
+        min_severity: Severity,
+        stylesheet: &Stylesheet,
+    ) -> io::Result<()> {
+        self.show_filtered_with_options(stream, min_severity, stylesheet, &RenderOptions::default())
+    }
+
+    /// Like [`.show_filtered()`](AnnotationList::show_filtered), but uses given layout
+    /// settings
+    pub fn show_filtered_with_options<W: Write + WriteColor>(
+        &self,
+        stream: W,
+        min_severity: Severity,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        self.show_blocks(
+            &mut Stream(stream),
+            stylesheet,
+            options,
+            self.blocks()
+                .filter(|(_idx, annotation)| annotation.severity.rank() >= min_severity.rank()),
+        )
+    }
+
+    /// Render message block by block, calling `f` with bytes of each annotation block as soon
+    /// as it's rendered, so the whole message is never buffered. Blocks are colorized using
+    /// ANSI escape codes; each block ends with a color reset, so blocks can be written
+    /// anywhere independently. Blank lines separating a block from the previous one are
+    /// included at its start.
+    /// ```rust
+    /// # use show_my_errors::{AnnotationList, Stylesheet};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("hello.txt", "Hello world!");
+    /// list.info(0..5, "greeting", None)?.info(6..11, "planet", None)?;
+    /// let mut blocks = 0;
+    /// list.show_each(&Stylesheet::colored(), |_bytes| blocks += 1)?;
+    /// assert_eq!(blocks, 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn show_each(&self, stylesheet: &Stylesheet, f: impl FnMut(&[u8])) -> io::Result<()> {
+        self.show_each_with_options(stylesheet, &RenderOptions::default(), f)
+    }
+
+    /// Like [`.show_each()`](AnnotationList::show_each), but uses given layout settings
+    pub fn show_each_with_options(
+        &self,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+        mut f: impl FnMut(&[u8]),
+    ) -> io::Result<()> {
+        let mut result = Ok(());
+        let mut buf = termcolor::Ansi::new(Vec::new());
+        self.for_each_block_events(stylesheet, options, self.blocks(), |events| {
+            if result.is_err() {
+                return;
+            }
+            buf.get_mut().clear();
+            result = write_events(&mut Stream(&mut buf), stylesheet, options, &events);
+            if result.is_ok() {
+                f(buf.get_ref());
+            }
+        });
+        result
+    }
+
+    /// Print annotations in compact form, one line per annotation, without source snippets:
+    /// `error: message (file:line:col)`, where message is the first line of
+    /// [annotation message](Annotation::message). This is useful for dense logs or `grep`-like
+    /// tools.
+    pub fn show_compact<W: Write + WriteColor>(
+        &self,
+        stream: W,
+        stylesheet: &Stylesheet,
+    ) -> io::Result<()> {
+        self.show_compact_with_options(stream, stylesheet, &RenderOptions::default())
+    }
+
+    /// Like [`.show_compact()`](AnnotationList::show_compact), but uses given layout settings,
+    /// e.g. [`RenderOptions::column_encoding`]
+    pub fn show_compact_with_options<W: Write + WriteColor>(
+        &self,
+        stream: W,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        write_events(
+            &mut Stream(stream),
+            stylesheet,
+            options,
+            &self.compact_events(stylesheet, options),
+        )
+    }
+
+    /// "Print" monochrome message in compact form to [`String`], see
+    /// [`.show_compact()`](AnnotationList::show_compact)
+    /// ```rust
+    /// # use show_my_errors::AnnotationList;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("hello.txt", "Hello world!");
+    /// list.warning(4..7, "punctuation problem", "you probably forgot a comma")?
+    ///     .error(11..12, None, "unexpected `!`")?;
+    /// assert_eq!(list.to_compact_string()?, "warning: punctuation problem (hello.txt:1:5)\n\
+    ///                                         error: unexpected `!` (hello.txt:1:12)\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_compact_string(&self) -> io::Result<String> {
+        self.to_compact_string_with_options(&RenderOptions::default())
+    }
+
+    /// Like [`.to_compact_string()`](AnnotationList::to_compact_string), but uses given layout
+    /// settings
+    pub fn to_compact_string_with_options(&self, options: &RenderOptions) -> io::Result<String> {
+        let mut buf = String::new();
+        let stylesheet = Stylesheet::monochrome();
+        write_events(
+            &mut buf,
+            &stylesheet,
+            options,
+            &self.compact_events(&stylesheet, options),
+        )?;
+        Ok(buf)
+    }
+
+    /// Print annotations in GCC-style short form, one line per annotation, without source
+    /// snippets: `file:line:col: severity: message`, where message is the first line of
+    /// [annotation message](Annotation::message). This format is understood by many editors
+    /// and is easy to `grep`.
+    pub fn show_short<W: Write + WriteColor>(
+        &self,
+        stream: W,
+        stylesheet: &Stylesheet,
+    ) -> io::Result<()> {
+        self.show_short_with_options(stream, stylesheet, &RenderOptions::default())
+    }
+
+    /// Like [`.show_short()`](AnnotationList::show_short), but uses given layout settings,
+    /// e.g. [`RenderOptions::column_encoding`]
+    pub fn show_short_with_options<W: Write + WriteColor>(
+        &self,
+        stream: W,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        write_events(
+            &mut Stream(stream),
+            stylesheet,
+            options,
+            &self.short_events(stylesheet, options),
+        )
+    }
+
+    /// "Print" monochrome message in short form to [`String`], see
+    /// [`.show_short()`](AnnotationList::show_short)
+    /// ```rust
+    /// # use show_my_errors::AnnotationList;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("hello.txt", "Hello world!");
+    /// list.warning(4..7, "punctuation problem", "you probably forgot a comma")?
+    ///     .error(11..12, None, "unexpected `!`")?;
+    /// assert_eq!(list.to_short_string()?, "hello.txt:1:5: warning: punctuation problem\n\
+    ///                                       hello.txt:1:12: error: unexpected `!`\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_short_string(&self) -> io::Result<String> {
+        self.to_short_string_with_options(&RenderOptions::default())
+    }
+
+    /// Like [`.to_short_string()`](AnnotationList::to_short_string), but uses given layout
+    /// settings
+    pub fn to_short_string_with_options(&self, options: &RenderOptions) -> io::Result<String> {
+        let mut buf = String::new();
+        let stylesheet = Stylesheet::monochrome();
+        write_events(
+            &mut buf,
+            &stylesheet,
+            options,
+            &self.short_events(&stylesheet, options),
+        )?;
+        Ok(buf)
+    }
+
+    /// All annotations in display order, along with indices of their lines
+    fn blocks(&self) -> impl Iterator<Item = (usize, &Annotation)> {
+        self.lines.iter().enumerate().flat_map(|(idx, line)| {
+            line.sorted_annotations()
+                .into_iter()
+                .map(move |ann| (idx, ann))
+        })
+    }
+
+    fn show_blocks<'b>(
+        &self,
+        stream: &mut impl Output,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+        blocks: impl IntoIterator<Item = (usize, &'b Annotation)>,
+    ) -> io::Result<()> {
+        write_events(
+            stream,
+            stylesheet,
+            options,
+            &self.blocks_events(stylesheet, options, blocks),
+        )
+    }
+
+    /// Show message using a buffer of `stream` and return whether it was colorized
+    fn show_bufwriter(&self, stream: BufferWriter, stylesheet: &Stylesheet) -> io::Result<bool> {
+        let mut buf = stream.buffer();
+        self.show(&mut buf, stylesheet)?;
+        stream.print(&buf)?;
+        Ok(buf.supports_color())
+    }
+
+    /// Print error message to stdout. Output will be colorized if stdout is a TTY, unless
+    /// overridden, see [`set_default_color_choice`]
+    pub fn show_stdout(&self, stylesheet: &Stylesheet) -> io::Result<()> {
+        self.show_stdout_colored(stylesheet).map(drop)
+    }
+
+    /// Print error message to stderr. Output will be colorized if stderr is a TTY, unless
+    /// overridden, see [`set_default_color_choice`]
+    pub fn show_stderr(&self, stylesheet: &Stylesheet) -> io::Result<()> {
+        self.show_stderr_colored(stylesheet).map(drop)
+    }
+
+    /// Like [`.show_stdout()`](AnnotationList::show_stdout), but also returns whether the
+    /// output was colorized
+    pub fn show_stdout_colored(&self, stylesheet: &Stylesheet) -> io::Result<bool> {
+        let color_choice = color::resolve_color_choice(None, atty::is(atty::Stream::Stdout));
+        self.show_bufwriter(termcolor::BufferWriter::stdout(color_choice), stylesheet)
+    }
+
+    /// Like [`.show_stderr()`](AnnotationList::show_stderr), but also returns whether the
+    /// output was colorized
+    pub fn show_stderr_colored(&self, stylesheet: &Stylesheet) -> io::Result<bool> {
+        let color_choice = color::resolve_color_choice(None, atty::is(atty::Stream::Stderr));
+        self.show_bufwriter(termcolor::BufferWriter::stderr(color_choice), stylesheet)
+    }
+
+    /// Print error message to stdout using given [`ColorChoice`], ignoring the process-wide
+    /// one. Returns whether the output was colorized.
+    pub fn show_stdout_with_color(
+        &self,
+        stylesheet: &Stylesheet,
+        color_choice: ColorChoice,
+    ) -> io::Result<bool> {
+        self.show_bufwriter(termcolor::BufferWriter::stdout(color_choice), stylesheet)
+    }
+
+    /// Print error message to stderr using given [`ColorChoice`], ignoring the process-wide
+    /// one. Returns whether the output was colorized.
+    pub fn show_stderr_with_color(
+        &self,
+        stylesheet: &Stylesheet,
+        color_choice: ColorChoice,
+    ) -> io::Result<bool> {
+        self.show_bufwriter(termcolor::BufferWriter::stderr(color_choice), stylesheet)
+    }
+
+    /// "Print" message to `Vec<u8>`, colorizing it using ANSI escape codes unless `color_choice`
+    /// is [`ColorChoice::Never`]. There is no terminal to detect capabilities of, so
+    /// [`ColorChoice::Auto`] is the same as [`ColorChoice::Always`] here.
+    pub fn to_bytes_with_color(
+        &self,
+        stylesheet: &Stylesheet,
+        color_choice: ColorChoice,
+    ) -> io::Result<Vec<u8>> {
+        self.to_bytes_with_options(stylesheet, color_choice, &RenderOptions::default())
+    }
+
+    /// Like [`.to_bytes_with_color()`](AnnotationList::to_bytes_with_color), but uses given
+    /// layout settings
+    pub fn to_bytes_with_options(
+        &self,
+        stylesheet: &Stylesheet,
+        color_choice: ColorChoice,
+        options: &RenderOptions,
+    ) -> io::Result<Vec<u8>> {
+        let mut buf = match color_choice {
+            ColorChoice::Never => termcolor::Buffer::no_color(),
+            _ => termcolor::Buffer::ansi(),
+        };
+        self.show_with_options(&mut buf, stylesheet, options)?;
+        Ok(buf.into_inner())
+    }
+
+    /// "Print" monochrome message to `Vec<u8>`
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        self.to_bytes_with_color(&Stylesheet::monochrome(), ColorChoice::Never)
+    }
+
+    /// "Print" message to `Vec<u8>`, colorizing it using ANSI escape codes
+    pub fn to_ansi_bytes(&self, stylesheet: &Stylesheet) -> io::Result<Vec<u8>> {
+        self.to_bytes_with_color(stylesheet, ColorChoice::Always)
+    }
+
+    /// "Print" monochrome message to [`String`]. Text is written to the string directly, so
+    /// this never fails in practice.
+    pub fn to_string(&self) -> io::Result<String> {
+        self.to_string_with_options(&RenderOptions::default())
+    }
+
+    /// Like [`.to_string()`](AnnotationList::to_string), but uses given layout settings
+    pub fn to_string_with_options(&self, options: &RenderOptions) -> io::Result<String> {
+        let mut buf = String::new();
+        self.show_blocks(&mut buf, &Stylesheet::monochrome(), options, self.blocks())?;
+        Ok(buf)
+    }
+
+    /// Append monochrome message to an existing [`String`]. Unlike
+    /// [`.to_string()`](AnnotationList::to_string), this uses the given stylesheet: its colors
+    /// are ignored, but other settings (e.g. [`Labels`]) are respected.
+    pub fn render_into(&self, buf: &mut String, stylesheet: &Stylesheet) {
+        self.render_into_with_options(buf, stylesheet, &RenderOptions::default())
+    }
+
+    /// Like [`.render_into()`](AnnotationList::render_into), but uses given layout settings
+    pub fn render_into_with_options(
+        &self,
+        buf: &mut String,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+    ) {
+        self.show_blocks(buf, stylesheet, options, self.blocks())
+            .expect("writing to String can't fail")
+    }
+
+    /// Append message to an existing [`String`], colorizing it using ANSI escape codes.
+    pub fn render_ansi_into(&self, buf: &mut String, stylesheet: &Stylesheet) -> io::Result<()> {
+        self.render_ansi_into_with_options(buf, stylesheet, &RenderOptions::default())
+    }
+
+    /// Like [`.render_ansi_into()`](AnnotationList::render_ansi_into), but uses given layout
+    /// settings
+    pub fn render_ansi_into_with_options(
+        &self,
+        buf: &mut String,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        let mut stream = Stream(termcolor::Ansi::new(StringWriter(buf)));
+        self.show_blocks(&mut stream, stylesheet, options, self.blocks())
+    }
+
+    /// "Print" message to [`String`], colorizing it using ANSI escape codes
+    pub fn to_ansi_string(&self, stylesheet: &Stylesheet) -> io::Result<String> {
+        self.to_ansi_string_with_options(stylesheet, &RenderOptions::default())
+    }
+
+    /// Like [`.to_ansi_string()`](AnnotationList::to_ansi_string), but uses given layout
+    /// settings
+    pub fn to_ansi_string_with_options(
+        &self,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+    ) -> io::Result<String> {
+        let mut buf = String::new();
+        self.render_ansi_into_with_options(&mut buf, stylesheet, options)?;
+        Ok(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    fn assert_start_content<'a>(line: &AnnotatedLine<'a>, start: usize, content: &'a str) {
+        assert_eq!(line.start(), start);
+        assert_eq!(line.content(), content);
+    }
+
+    fn create_list() -> AnnotationList<'static> {
+        AnnotationList::new("test.txt", "\nstring\nwith\nmany\n\nnewlines\n\n")
+    }
+
+    #[test]
+    fn test_new_many_newlines() {
+        let annotation_list = create_list();
+        let mut lines = annotation_list.annotated_lines().iter();
+        assert_start_content(lines.next().unwrap(), 0, "\n");
+        assert_start_content(lines.next().unwrap(), 1, "string\n");
+        assert_start_content(lines.next().unwrap(), 8, "with\n");
+        assert_start_content(lines.next().unwrap(), 13, "many\n");
+        assert_start_content(lines.next().unwrap(), 18, "\n");
+        assert_start_content(lines.next().unwrap(), 19, "newlines\n");
+        assert_start_content(lines.next().unwrap(), 28, "\n");
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn test_new_without_newlines() {
+        let annotation_list = AnnotationList::new("filename", "string without newlines");
+        let mut lines = annotation_list.annotated_lines().iter();
+        assert_start_content(lines.next().unwrap(), 0, "string without newlines");
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn test_new_trailing_newline() {
+        let annotation_list = AnnotationList::new("filename", "string with trailing newline\n");
+        let mut lines = annotation_list.annotated_lines().iter();
+        assert_start_content(lines.next().unwrap(), 0, "string with trailing newline\n");
+    }
+
+    #[test]
+    fn test_new_leading_newline() {
+        let annotation_list = AnnotationList::new("filename", "\nstring with leading newline");
         let mut lines = annotation_list.annotated_lines().iter();
         assert_start_content(lines.next().unwrap(), 0, "\n");
         assert_start_content(lines.next().unwrap(), 1, "string with leading newline");
@@ -409,9 +1664,9 @@ mod tests {
 
         for (idx, line) in list.annotated_lines().iter().enumerate() {
             match idx {
-                1 => assert_eq!(line.annotations(), &[ann1.clone()]),
+                1 => assert_eq!(line.annotations(), std::slice::from_ref(&ann1)),
                 3 => assert_eq!(line.annotations(), &[ann2.clone(), ann4.clone()]),
-                5 => assert_eq!(line.annotations(), &[ann3.clone()]),
+                5 => assert_eq!(line.annotations(), std::slice::from_ref(&ann3)),
                 _ => assert_eq!(line.annotations(), &[]),
             }
         }
@@ -428,16 +1683,21 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::reversed_empty_ranges)]
     fn test_invalid_adds() -> Result<()> {
         let mut list = create_list();
         assert_eq!(
             list.add(Annotation::info(1..10, "test", "ann")?)
                 .unwrap_err(),
-            Error::MultilineRange(1, 10)
+            AddError {
+                error: Error::MultilineRange(1, 10),
+                annotation: Box::new(Annotation::info(1..10, "test", "ann")?),
+            }
         );
         assert_eq!(
             list.add(Annotation::info(1000..1001, "test", "ann")?)
-                .unwrap_err(),
+                .unwrap_err()
+                .error,
             Error::AfterStringEnd(1000, 1001)
         );
         assert_eq!(
@@ -447,6 +1707,21 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_find_overlaps() -> Result<()> {
+        let mut list = create_list();
+        list.info(1..3, "test1", "ann1")?
+            .warning(13..17, "test2", "ann2")?
+            .error(14..16, "test3", None)?
+            .error(17..17, "test4", None)?
+            .info(8..8, "test5", None)?
+            .info(2..5, "test6", None)?;
+        // Display order: ann1, test6, test5, ann2, test3, test4
+        assert_eq!(list.find_overlaps(), vec![(0, 1), (3, 4)]);
+        assert_eq!(create_list().find_overlaps(), vec![]);
+        Ok(())
+    }
+
     #[test]
     fn test_to_string() -> Result<()> {
         let mut list = create_list();
@@ -488,4 +1763,2599 @@ error: test3
         assert_eq!(list.to_string().unwrap(), result);
         Ok(())
     }
+
+    #[test]
+    fn test_show_grouped() -> Result<()> {
+        let mut list = create_list();
+        list.info(1..3, "test1", None)?
+            .warning(13..17, "test2", None)?
+            .error(19..20, "test3", None)?
+            .warning(8..9, "test4", None)?
+            .error(14..16, "test5", None)?;
+        let mut buf = termcolor::Buffer::no_color();
+        list.show_grouped(&mut buf, &Stylesheet::monochrome())
+            .unwrap();
+        let output = String::from_utf8(buf.into_inner()).unwrap();
+        let headers: Vec<_> = output
+            .lines()
+            .filter(|line| line.contains(": test"))
+            .collect();
+        assert_eq!(
+            headers,
+            [
+                "error: test5",
+                "error: test3",
+                "warning: test4",
+                "warning: test2",
+                "info: test1"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_into() -> Result<()> {
+        let mut list = create_list();
+        list.info(1..3, "test1", "ann1")?
+            .warning(13..17, "test2", "ann2")?
+            .error(19..20, None, None)?;
+        let mut buf = String::from("prefix\n");
+        list.render_into(&mut buf, &Stylesheet::colored());
+        assert_eq!(buf, format!("prefix\n{}", list.to_string().unwrap()));
+        Ok(())
+    }
+
+    fn ansi(spec: &termcolor::ColorSpec) -> String {
+        let mut buf = termcolor::Buffer::ansi();
+        buf.set_color(spec).unwrap();
+        String::from_utf8(buf.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn test_highlight_span() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "hello world\n");
+        list.error(6..11, "header", "text")?;
+        let mut stylesheet = Stylesheet::colored();
+        let plain = list.to_ansi_string(&stylesheet).unwrap();
+        assert!(plain.contains(&format!("{}hello world\n", ansi(&stylesheet.content))));
+
+        stylesheet.highlight_span = true;
+        let highlighted = list.to_ansi_string(&stylesheet).unwrap();
+        assert!(highlighted.contains(&format!(
+            "{}hello {}world\n",
+            ansi(&stylesheet.content),
+            ansi(&stylesheet.error),
+        )));
+        Ok(())
+    }
+
+    #[test]
+    fn test_show_range() -> Result<()> {
+        let mut list = AnnotationList::new("hello.txt", "Hello world!");
+        list.warning(4..7, None, "text")?.info(0..0, None, None)?;
+        assert!(!list.to_string().unwrap().contains("[4..7]"));
+        let options = RenderOptions {
+            show_range: true,
+            ..RenderOptions::default()
+        };
+        let output = list.to_string_with_options(&options).unwrap();
+        assert!(output.contains("   |     ^^^ text [4..7]\n"));
+        assert!(output.contains("   | [0..0]\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_line() -> Result<()> {
+        let mut list = create_list();
+        list.info(0..0, "consider adding some text", None)?
+            .info(18..18, None, "not shown")?;
+        assert_eq!(
+            list.to_string().unwrap(),
+            r#"info: consider adding some text
+  --> test.txt:1:1
+   |
+ 1 |
+   |
+
+info:
+  --> test.txt:5:1
+   |
+ 5 |
+   |
+"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_header_only_at_eof() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "hello world\n");
+        list.warning(11..11, "missing semicolon", None)?;
+        assert_eq!(
+            list.to_string().unwrap(),
+            r#"warning: missing semicolon
+  --> fname:1:12
+   |
+ 1 | hello world
+   |            ^
+"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_bytes_with_color() -> Result<()> {
+        let mut list = create_list();
+        list.error(19..20, "test", "text")?;
+        let stylesheet = Stylesheet::colored();
+        assert_eq!(
+            list.to_bytes_with_color(&stylesheet, ColorChoice::Never)
+                .unwrap(),
+            list.to_bytes().unwrap()
+        );
+        assert_eq!(
+            list.to_bytes_with_color(&stylesheet, ColorChoice::Always)
+                .unwrap(),
+            list.to_ansi_bytes(&stylesheet).unwrap()
+        );
+        assert_ne!(
+            list.to_bytes().unwrap(),
+            list.to_ansi_bytes(&stylesheet).unwrap()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_multiline_text() -> Result<()> {
+        let mut list = AnnotationList::new("hello.txt", "Hello world!");
+        list.warning(4..7, None, "you probably forgot a comma\nor maybe not")?;
+        assert_eq!(
+            list.to_string().unwrap(),
+            r#"warning:
+  --> hello.txt:1:5
+   |
+ 1 | Hello world!
+   |     ^^^ you probably forgot a comma
+   |         or maybe not
+"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_lines() -> Result<()> {
+        let source = "\nstring\nwith\nmany\n\nnewlines\n\n";
+        let expected = create_list();
+        let lines = expected
+            .annotated_lines()
+            .iter()
+            .map(|line| (line.start(), line.content()))
+            .collect::<Vec<_>>();
+        assert_eq!(AnnotationList::from_lines("test.txt", lines)?, expected);
+        assert_eq!(expected, AnnotationList::new("test.txt", source));
+
+        let mut list = AnnotationList::from_lines("fname", vec![(10, "abc\n"), (20, "def")])?;
+        list.info(11..12, None, None)?.info(21..23, None, None)?;
+        assert_eq!(
+            list.info(5..6, None, None).unwrap_err(),
+            Error::BeforeStringStart(5, 6)
+        );
+        assert_eq!(
+            AnnotationList::from_lines("fname", vec![(0, "abc\n"), (3, "def")]).unwrap_err(),
+            Error::UnorderedLines(4, 3)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_linenr_alignment() -> Result<()> {
+        let source = "1\n2\n3\n4\n5\n6\n7\n8\nnine\nten\n";
+        let mut list = AnnotationList::new("fname", source);
+        list.warning(16..20, None, "ann9")?
+            .error(21..24, None, "ann10")?;
+        assert_eq!(
+            list.to_string().unwrap(),
+            r#"warning:
+   --> fname:9:1
+    |
+  9 | nine
+    | ^^^^ ann9
+
+error:
+   --> fname:10:1
+    |
+ 10 | ten
+    | ^^^ ann10
+"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[allow(clippy::reversed_empty_ranges)]
+    fn test_builder() -> Result<()> {
+        assert_eq!(
+            Annotation::builder(1..3).build()?,
+            Annotation::error(1..3, None, None)?
+        );
+        assert_eq!(
+            Annotation::builder(1..3)
+                .severity(Severity::Info)
+                .text("text")
+                .build()?,
+            Annotation::info(1..3, None, "text")?
+        );
+        assert_eq!(
+            Annotation::builder(3..1).header("header").build(),
+            Err(Error::InvalidRange(3, 1))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_ansi_into() -> Result<()> {
+        let mut list = create_list();
+        list.info(1..3, "test1", "ann1")?
+            .warning(13..17, "test2", "ann2\nmore")?
+            .error(19..20, None, None)?;
+        let stylesheet = Stylesheet::colored();
+        let mut buf = String::new();
+        list.render_ansi_into(&mut buf, &stylesheet).unwrap();
+        assert_eq!(buf, list.to_ansi_string(&stylesheet).unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotate_matches() -> Result<()> {
+        let source = "one two\nthree two\ntwo\nfour";
+        let mut list = AnnotationList::new("fname", source);
+        let matches = source
+            .match_indices("two")
+            .map(|(idx, m)| idx..idx + m.len())
+            .chain(std::iter::once(
+                source.find("two\nfour").unwrap()..source.len(),
+            ));
+        list.annotate_matches(matches, Severity::Warning, "found two", None)?;
+        let lines: Vec<_> = list
+            .annotated_lines()
+            .iter()
+            .map(|line| line.annotations().len())
+            .collect();
+        assert_eq!(lines, [1, 1, 1, 0]);
+        assert!(list
+            .annotations()
+            .all(|ann| ann.severity == Severity::Warning));
+        assert_eq!(
+            list.annotate_matches(std::iter::once(100..101), Severity::Info, None, None)
+                .unwrap_err(),
+            Error::AfterStringEnd(100, 101)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_linenr_by_severity() -> Result<()> {
+        let mut list = create_list();
+        list.info(13..14, None, None)?
+            .error(14..15, None, None)?
+            .warning(19..20, None, None)?;
+        let stylesheet = Stylesheet::colored();
+        let colored_linenr = |stylesheet: &Stylesheet, color: &termcolor::ColorSpec, nr| {
+            format!("{}{}{} |", ansi(color), nr, ansi(&stylesheet.linenr))
+        };
+        let output = list.to_ansi_string(&stylesheet).unwrap();
+        assert!(!output.contains(&colored_linenr(&stylesheet, &stylesheet.error, 4)));
+        let options = RenderOptions {
+            linenr_by_severity: true,
+            ..RenderOptions::default()
+        };
+        let output = list
+            .to_ansi_string_with_options(&stylesheet, &options)
+            .unwrap();
+        assert!(output.contains(&colored_linenr(&stylesheet, &stylesheet.error, 4)));
+        assert!(output.contains(&colored_linenr(&stylesheet, &stylesheet.warning, 6)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_extend() -> Result<()> {
+        let mut list = create_list();
+        assert_eq!(
+            list.extend(vec![
+                (1..3, Severity::Info, "test1".into()),
+                (13..17, Severity::Warning, "test2".into()),
+            ]),
+            Ok(2)
+        );
+        assert_eq!(
+            list.extend(vec![
+                (19..20, Severity::Error, "test3".into()),
+                (1000..1001, Severity::Error, "test4".into()),
+                (1..3, Severity::Error, "test5".into()),
+            ]),
+            Err((1, Error::AfterStringEnd(1000, 1001)))
+        );
+        let mut expected = create_list();
+        expected
+            .info(1..3, "test1", None)?
+            .warning(13..17, "test2", None)?
+            .error(19..20, "test3", None)?;
+        assert_eq!(list, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotations_on_line() -> Result<()> {
+        let mut list = create_list();
+        list.info(1..3, "test1", "ann1")?
+            .warning(13..17, "test2", "ann2")?
+            .error(14..16, "test4", "ann4")?;
+        assert_eq!(
+            list.annotations_on_line(2),
+            &[Annotation::info(1..3, "test1", "ann1")?]
+        );
+        assert_eq!(list.annotations_on_line(4).len(), 2);
+        assert_eq!(list.annotations_on_line(3), &[]);
+        assert_eq!(list.annotations_on_line(0), &[]);
+        assert_eq!(list.annotations_on_line(100), &[]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_directory_color() -> Result<()> {
+        let mut list = AnnotationList::new("src/main.rs", "fn main() {}");
+        list.error(0..2, None, None)?;
+        let mut stylesheet = Stylesheet::colored();
+        let output = list.to_ansi_string(&stylesheet).unwrap();
+        assert!(output.contains(&format!("{}src/main.rs:1:1", ansi(&stylesheet.filename))));
+
+        let mut directory = termcolor::ColorSpec::new();
+        directory.set_dimmed(true);
+        stylesheet.directory = Some(directory.clone());
+        let output = list.to_ansi_string(&stylesheet).unwrap();
+        assert!(output.contains(&format!(
+            "{}src/{}main.rs:1:1",
+            ansi(&directory),
+            ansi(&stylesheet.filename)
+        )));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "sarif")]
+    fn test_to_sarif() -> Result<()> {
+        let mut list = AnnotationList::new("src/\"quoted\".rs", "fn main() {\n    let é = 1;\n}");
+        list.warning(25..26, "unused value", "here")?
+            .error(28..29, None, "unexpected\nbrace")?;
+        assert_eq!(
+            list.to_sarif(),
+            concat!(
+                r#"{"$schema":"https://json.schemastore.org/sarif-2.1.0.json","version":"2.1.0","#,
+                r#""runs":[{"tool":{"driver":{"name":"show-my-errors"}},"columnKind":"unicodeCodePoints","#,
+                r#""results":[{"level":"warning","message":{"text":"unused value"},"locations":[{"#,
+                r#""physicalLocation":{"artifactLocation":{"uri":"src/\"quoted\".rs"},"#,
+                r#""region":{"startLine":2,"startColumn":13,"endLine":2,"endColumn":14}}}]},"#,
+                r#"{"level":"error","message":{"text":"unexpected\nbrace"},"locations":[{"#,
+                r#""physicalLocation":{"artifactLocation":{"uri":"src/\"quoted\".rs"},"#,
+                r#""region":{"startLine":3,"startColumn":1,"endLine":3,"endColumn":2}}}]}]}]}"#,
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_line_index_for() {
+        let list = create_list();
+        for (idx, line) in list.annotated_lines().iter().enumerate() {
+            assert_eq!(list.line_index_for(line.start()), Ok(idx));
+            let last = line.start() + line.content().len() - 1;
+            assert_eq!(list.line_index_for(last), Ok(idx));
+        }
+        assert_eq!(list.line_index_for(29), Err(Error::AfterStringEnd(29, 29)));
+
+        let list = AnnotationList::from_lines("fname", vec![(10, "abc\n"), (20, "def")]).unwrap();
+        assert_eq!(list.line_index_for(9), Err(Error::BeforeStringStart(9, 9)));
+        assert_eq!(list.line_index_for(14), Err(Error::AfterStringEnd(14, 14)));
+        assert_eq!(list.line_index_for(20), Ok(1));
+        assert_eq!(list.line_index_for(23), Err(Error::AfterStringEnd(23, 23)));
+    }
+
+    #[test]
+    fn test_visible_whitespace() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "let x = 1; \t \nnext line");
+        list.warning(10..13, "trailing whitespace", None)?;
+        let mut stylesheet = Stylesheet::monochrome();
+        stylesheet.visible_whitespace = true;
+        let mut output = String::new();
+        list.render_into(&mut output, &stylesheet);
+        assert_eq!(
+            output,
+            r#"warning: trailing whitespace
+  --> fname:1:11
+   |
+ 1 | let x = 1;·→·
+   |           ^^^
+"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_separators() -> Result<()> {
+        let mut list = AnnotationList::new("src\\main.rs", "fn main() {}");
+        list.error(0..2, None, None)?;
+        let output = list.to_string().unwrap();
+        assert!(output.contains("--> src\\main.rs:1:1\n"));
+        let options = RenderOptions {
+            normalize_separators: true,
+            ..RenderOptions::default()
+        };
+        let output = list.to_string_with_options(&options).unwrap();
+        assert!(output.contains("--> src/main.rs:1:1\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_span_conversions() -> Result<()> {
+        let mut list = create_list();
+        list.info(1..=2, "test1", "ann1")?
+            .warning(Span::new(13, 4), "test2", "ann2")?
+            .error(Span::inclusive(19..=19), "test3", None)?;
+        let mut expected = create_list();
+        expected
+            .info(1..3, "test1", "ann1")?
+            .warning(13..17, "test2", "ann2")?
+            .error(19..20, "test3", None)?;
+        assert_eq!(list, expected);
+        assert_eq!(Span::new(5, 0), Span::from(5..5));
+        assert!(Span::new(5, 0).is_empty());
+        assert_eq!(Span::inclusive(5..=7).len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_indent() -> Result<()> {
+        let mut list = create_list();
+        list.info(1..3, "test1", "ann1\nmore")?
+            .error(19..20, "test3", None)?;
+        let options = RenderOptions {
+            indent: "    ".into(),
+            ..RenderOptions::default()
+        };
+        let output = list.to_string_with_options(&options).unwrap();
+        let expected: String = list
+            .to_string()
+            .unwrap()
+            .lines()
+            .map(|line| match line {
+                "" => "\n".to_string(),
+                line => format!("    {}\n", line),
+            })
+            .collect();
+        assert_eq!(output, expected);
+
+        let stylesheet = Stylesheet::colored();
+        let colored = list
+            .to_ansi_string_with_options(&stylesheet, &options)
+            .unwrap();
+        assert!(colored.starts_with(&format!("    {}info", ansi(&stylesheet.info))));
+        // Prefix goes after color reset
+        let reset = "\x1b[0m";
+        assert!(colored.contains(&format!("\n{}    {}", reset, ansi(&stylesheet.linenr))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_at_final_positions() -> Result<()> {
+        // Source is 29 bytes long and ends with "\n\n": the last line is an empty line 7
+        let mut list = create_list();
+        list.info(27..28, "last newline of line 6", None)?
+            .info(28..28, "empty line 7", None)?
+            .info(28..29, "newline of line 7", None)?;
+        assert_eq!(list.annotations_on_line(6).len(), 1);
+        assert_eq!(list.annotations_on_line(7).len(), 2);
+        assert_eq!(list.annotations_on_line(8), &[]);
+        assert_eq!(
+            list.info(29..29, None, None).unwrap_err(),
+            Error::AfterStringEnd(29, 29)
+        );
+        assert_eq!(
+            list.info(30..31, None, None).unwrap_err(),
+            Error::AfterStringEnd(30, 31)
+        );
+        assert_eq!(
+            list.info(28..30, None, None).unwrap_err(),
+            Error::MultilineRange(28, 30)
+        );
+
+        let mut list = AnnotationList::new("fname", "no trailing newline");
+        list.info(18..19, None, None)?;
+        assert_eq!(
+            list.info(19..19, None, None).unwrap_err(),
+            Error::AfterStringEnd(19, 19)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_source() {
+        for source in &[
+            "\nstring\nwith\nmany\n\nnewlines\n\n",
+            "no newlines",
+            "trailing newline\n",
+            "multi-byte é\nchars ü",
+        ] {
+            assert_eq!(AnnotationList::new("fname", source).source(), *source);
+        }
+    }
+
+    #[test]
+    fn test_separator_lines() -> Result<()> {
+        let mut list = AnnotationList::new("hello.txt", "Hello world!");
+        list.warning(4..7, "punctuation problem", "you probably forgot a comma")?
+            .info(0..0, "consider adding some translations", None)?;
+        let stylesheet = Stylesheet::monochrome();
+        let mut options = RenderOptions {
+            separator_lines: 0,
+            ..RenderOptions::default()
+        };
+        let render = |options: &RenderOptions| {
+            let mut buf = termcolor::Buffer::no_color();
+            list.show_with_options(&mut buf, &stylesheet, options)
+                .unwrap();
+            String::from_utf8(buf.into_inner()).unwrap()
+        };
+        assert_eq!(
+            render(&options),
+            r#"warning: punctuation problem
+  --> hello.txt:1:5
+   |
+ 1 | Hello world!
+   |     ^^^ you probably forgot a comma
+info: consider adding some translations
+  --> hello.txt:1:1
+   |
+ 1 | Hello world!
+   |
+"#
+        );
+        options.separator_lines = 2;
+        assert!(render(&options).contains("comma\n\n\ninfo"));
+        assert_eq!(render(&RenderOptions::default()), list.to_string().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_priority() -> Result<()> {
+        let mut list = AnnotationList::new("hello.txt", "Hello world!");
+        list.warning(0..5, "added first", None)?
+            .add(
+                Annotation::builder(6..11)
+                    .severity(Severity::Warning)
+                    .header("high priority")
+                    .priority(10)
+                    .build()?,
+            )?
+            .warning(11..12, "added last", None)?;
+        let headers: Vec<_> = list
+            .annotations()
+            .map(|ann| ann.header.as_deref().unwrap())
+            .collect();
+        assert_eq!(headers, ["high priority", "added first", "added last"]);
+        let output = list.to_string().unwrap();
+        assert!(output.find("high priority").unwrap() < output.find("added first").unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_char_ranges() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "café\nthé au lait");
+        assert_eq!(list.char_range_to_bytes(0..4), Ok(0..5));
+        assert_eq!(list.char_range_to_bytes(5..8), Ok(6..10));
+        assert_eq!(list.char_range_to_bytes(8..8), Ok(10..10));
+        assert_eq!(list.char_range_to_bytes(16..16), Ok(18..18));
+        assert_eq!(
+            list.char_range_to_bytes(10..17),
+            Err(Error::AfterStringEnd(10, 17))
+        );
+        list.add_chars(Annotation::warning(5..8, "tea", None)?)?;
+        assert_eq!(
+            list.annotations_on_line(2),
+            &[Annotation::warning(6..10, "tea", None)?]
+        );
+        let rejected = list
+            .add_chars(Annotation::warning(17..18, None, None)?)
+            .unwrap_err();
+        assert_eq!(rejected.error, Error::AfterStringEnd(17, 18));
+        assert_eq!(rejected.annotation.range(), &(17..18));
+        Ok(())
+    }
+
+    #[test]
+    fn test_char_width() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "é\tx");
+        list.error(3..4, None, "here")?;
+        assert!(list.to_string().unwrap().contains("\n   |   ^ here\n"));
+        let options = RenderOptions {
+            char_width: |c| if c == '\t' { 4 } else { 1 },
+            ..RenderOptions::default()
+        };
+        let output = list.to_string_with_options(&options).unwrap();
+        assert!(output.contains("\n   |      ^ here\n"));
+        let mut list = AnnotationList::new("fname", "héllo wörld");
+        list.error(7..13, None, "here")?;
+        assert!(list
+            .to_string()
+            .unwrap()
+            .contains("\n   |       ^^^^^ here\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_grouped_label_alignment() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "let value = 1;");
+        list.error(4..9, None, "long")?.error(0..3, None, "short")?;
+        let mut buf = termcolor::Buffer::no_color();
+        list.show_grouped(&mut buf, &Stylesheet::monochrome())
+            .unwrap();
+        let output = String::from_utf8(buf.into_inner()).unwrap();
+        assert!(output.contains("\n   |     ^^^^^ long\n"));
+        assert!(output.contains("\n   | ^^^       short\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_suggested_exit_code() -> Result<()> {
+        let mut list = create_list();
+        assert_eq!(list.max_severity(), None);
+        assert_eq!(list.suggested_exit_code(), 0);
+        list.warning(1..3, "test1", None)?
+            .info(8..9, "test2", None)?;
+        assert_eq!(list.max_severity(), Some(Severity::Warning));
+        assert_eq!(list.suggested_exit_code(), 0);
+        list.error(13..17, "test3", None)?;
+        assert_eq!(list.max_severity(), Some(Severity::Error));
+        assert_eq!(list.suggested_exit_code(), 1);
+
+        // Custom severity with the rank of an error sorts below it, but is just as severe
+        let blocker = Severity::Custom {
+            name: "aaa",
+            rank: Severity::Error.rank(),
+        };
+        assert!(blocker < Severity::Error);
+        let mut list = create_list();
+        list.add(Annotation::new(1..3, blocker, "test4", None)?)?;
+        assert_eq!(list.suggested_exit_code(), 1);
+        let mut buf = termcolor::Buffer::no_color();
+        list.show_filtered(&mut buf, Severity::Error, &Stylesheet::monochrome())
+            .unwrap();
+        assert!(String::from_utf8(buf.into_inner())
+            .unwrap()
+            .contains("aaa: test4"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_line_index() {
+        let source = "\nstring\nwith\nmany\n\nnewlines\n\n";
+        let index = LineIndex::new(source);
+        assert_eq!(index.line_count(), 7);
+        assert_eq!(
+            AnnotationList::with_index("test.txt", source, &index),
+            create_list()
+        );
+        assert_eq!(index.locate(0), Some((1, 1)));
+        assert_eq!(index.locate(3), Some((2, 3)));
+        assert_eq!(index.locate(source.len()), None);
+        assert_eq!(index.offset_at(2, 3), Some(3));
+        assert_eq!(index.offset_at(2, 8), None);
+        assert_eq!(index.offset_at(0, 1), None);
+        assert_eq!(index.offset_at(8, 1), None);
+        assert_eq!(LineIndex::new("").line_count(), 1);
+    }
+
+    #[test]
+    fn test_show_location() -> Result<()> {
+        let mut list = create_list();
+        list.warning(1..3, "test1", "text")?
+            .error(13..17, "test2", None)?;
+        let full = list.to_string().unwrap();
+        let options = RenderOptions {
+            show_location: false,
+            ..RenderOptions::default()
+        };
+        let mut output = String::new();
+        list.render_into_with_options(&mut output, &Stylesheet::monochrome(), &options);
+        let expected: Vec<_> = full.lines().filter(|line| !line.contains("-->")).collect();
+        assert_eq!(output.lines().collect::<Vec<_>>(), expected);
+        assert_ne!(output.lines().count(), full.lines().count());
+        Ok(())
+    }
+
+    #[test]
+    fn test_expand_span() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "use foo;");
+        list.warning(4..7, "`{span}` is deprecated", "replace `{span}`")?;
+        let output = list.to_string().unwrap();
+        assert!(output.contains("warning: `{span}` is deprecated\n"));
+        let options = RenderOptions {
+            expand_span: true,
+            ..RenderOptions::default()
+        };
+        let output = list.to_string_with_options(&options).unwrap();
+        assert!(output.contains("warning: `foo` is deprecated\n"));
+        assert!(output.contains("^^^ replace `foo`\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_clamp_carets() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "short\nline");
+        // Bypass validation to simulate a range which slipped past it
+        list.lines[0]
+            .annotations
+            .push(Annotation::error(2..100, None, "here")?);
+        let output = list.to_string().unwrap();
+        assert!(output.contains("\n   |   ^^^ here\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_show_filtered() -> Result<()> {
+        let mut list = create_list();
+        list.info(1..3, "test1", None)?
+            .warning(13..17, "test2", None)?
+            .info(19..20, "test3", None)?
+            .error(25..26, "test4", None)?;
+        let mut buf = termcolor::Buffer::no_color();
+        list.show_filtered(&mut buf, Severity::Warning, &Stylesheet::monochrome())
+            .unwrap();
+        let output = String::from_utf8(buf.into_inner()).unwrap();
+        let mut expected = create_list();
+        expected
+            .warning(13..17, "test2", None)?
+            .error(25..26, "test4", None)?;
+        assert_eq!(output, expected.to_string().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_stylesheet_presets() -> Result<()> {
+        let dracula = Stylesheet::dracula();
+        assert_eq!(
+            dracula.error.fg(),
+            Some(&termcolor::Color::Rgb(0xff, 0x55, 0x55))
+        );
+        assert!(dracula.error.bold());
+        let high_contrast = Stylesheet::high_contrast();
+        assert_eq!(high_contrast.error.fg(), Some(&termcolor::Color::Red));
+        assert!(high_contrast.error.intense() && !high_contrast.error.underline());
+        assert!(high_contrast.warning.bold() && high_contrast.warning.intense());
+        assert!(high_contrast.highlight_span);
+        assert_eq!(high_contrast.content, termcolor::ColorSpec::new());
+        // Only carets are underlined, not the header or the annotation text
+        let mut list = AnnotationList::new("fname", "let x = 1;\nlet y = {\n};\n");
+        list.error(4..5, "unused", "here")?
+            .add_multiline(Annotation::error(19..22, None, None)?)?;
+        let output = list.to_ansi_string(&high_contrast).unwrap();
+        let error = ansi(&high_contrast.error);
+        let mut caret = high_contrast.error.clone();
+        caret.set_underline(true);
+        let caret = ansi(&caret);
+        assert!(output.contains(&format!("{}error: unused", error)));
+        assert!(output.contains(&format!("{}^{} here", caret, error)));
+        assert!(output.contains(&format!("__{}^", caret)));
+        assert_eq!(output.matches(&caret).count(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_show_content() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "a,b,c,d");
+        list.error(4..5, "bad cell", "here")?;
+        let options = RenderOptions {
+            show_content: false,
+            ..RenderOptions::default()
+        };
+        let output = list.to_string_with_options(&options).unwrap();
+        assert_eq!(
+            output,
+            "error: bad cell\n  --> fname:1:5\n   |\n   |     ^ here\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_same_location() -> Result<()> {
+        let first = Annotation::warning(1..3, "unused", None)?;
+        let reworded = Annotation::warning(1..3, "never used", "remove it")?;
+        let other_severity = Annotation::error(1..3, "unused", None)?;
+        assert!(first.same_location(&reworded) && first != reworded);
+        assert!(!first.same_location(&other_severity));
+        let mut list = create_list();
+        list.add(first.clone())?
+            .add(other_severity.clone())?
+            .add(reworded)?
+            .add(first.clone())?;
+        assert_eq!(list.dedup_locations(), 2);
+        assert_eq!(
+            list.annotations().cloned().collect::<Vec<_>>(),
+            [first, other_severity]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_file_report() -> Result<()> {
+        let mut first = AnnotationList::new("b.txt", "second file");
+        first.error(0..6, "test1", None)?;
+        let mut second = AnnotationList::new("a.txt", "first file");
+        second.warning(0..5, "test2", None)?;
+        let report: MultiFileReport = vec![first, create_list(), second].into_iter().collect();
+        let output = report.to_string().unwrap();
+        let mut expected = report.lists()[2].to_string().unwrap();
+        expected.push('\n');
+        expected.push_str(&report.lists()[0].to_string().unwrap());
+        assert_eq!(output, expected);
+        assert_eq!(MultiFileReport::new().to_string().unwrap(), "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_file_coalesce() -> Result<()> {
+        let source = "fn main() {\n    let x = 1;\n}\n";
+        let mut first = AnnotationList::new("a.rs", source);
+        first.warning(20..21, "unused variable", None)?;
+        let mut second = AnnotationList::new("a.rs", source);
+        second.error(0..2, "test", None)?;
+        let mut other = AnnotationList::new("a.rs", "fn main() {}");
+        other.info(3..7, "different source", None)?;
+        let mut report: MultiFileReport = vec![first, other, second].into_iter().collect();
+        assert_eq!(report.coalesce(), 1);
+        assert_eq!(report.lists().len(), 2);
+        let output = report.lists()[0].to_string().unwrap();
+        assert_eq!(output.matches("--> a.rs").count(), 2);
+        assert!(output.contains("error: test"));
+        assert!(output.contains("warning: unused variable"));
+        assert_eq!(report.coalesce(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_column_override() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "\tx = 1");
+        list.add(Annotation::builder(1..2).header("test").build()?)?;
+        let default = list.to_string().unwrap();
+        assert!(default.contains("--> fname:1:2\n"));
+        let mut list = AnnotationList::new("fname", "\tx = 1");
+        list.add(Annotation::builder(1..2).header("test").column(5).build()?)?;
+        let overridden = list.to_string().unwrap();
+        assert_eq!(
+            overridden,
+            default.replace("--> fname:1:2\n", "--> fname:1:5\n")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_show_each() -> Result<()> {
+        let mut list = create_list();
+        list.info(1..3, "test1", None)?
+            .warning(13..17, "test2", "text")?
+            .error(19..20, "test3", None)?;
+        let stylesheet = Stylesheet::colored();
+        let mut blocks = vec![];
+        list.show_each(&stylesheet, |bytes| {
+            blocks.push(String::from_utf8(bytes.to_vec()).unwrap())
+        })
+        .unwrap();
+        assert_eq!(blocks.len(), 3);
+        for block in &blocks {
+            assert!(block.ends_with("\x1b[0m"));
+        }
+        assert!(blocks[1].starts_with("\x1b[0m\n"));
+        let strip = |s: &str| s.replace("\x1b[0m", "");
+        assert_eq!(
+            strip(&blocks.concat()),
+            strip(&list.to_ansi_string(&stylesheet).unwrap())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_severity() {
+        assert_eq!("help".parse(), Ok(Severity::Help));
+        assert_eq!("Note".parse(), Ok(Severity::Note));
+        assert_eq!("info".parse(), Ok(Severity::Info));
+        assert_eq!("WARNING".parse(), Ok(Severity::Warning));
+        assert_eq!("warn".parse(), Ok(Severity::Warning));
+        assert_eq!("Error".parse(), Ok(Severity::Error));
+        assert_eq!("err".parse(), Ok(Severity::Error));
+        let err = "fatal".parse::<Severity>().unwrap_err();
+        assert_eq!(err, ParseSeverityError("fatal".into()));
+        assert_eq!(
+            err.to_string(),
+            "unknown severity `fatal`, expected `help`, `note`, `info`, `warning` or `error`"
+        );
+    }
+
+    #[test]
+    fn test_show_ruler() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "let value = 1;");
+        list.error(12..13, None, "here")?;
+        let options = RenderOptions {
+            show_ruler: true,
+            ..RenderOptions::default()
+        };
+        let output = list.to_string_with_options(&options).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(
+            lines[3..],
+            [
+                "   |          11111",
+                "   | 12345678901234",
+                " 1 | let value = 1;",
+                "   |             ^ here",
+            ]
+        );
+        // Ruler columns are display columns, not bytes
+        let mut list = AnnotationList::new("fname", "héllo wörld");
+        list.error(8..10, None, "here")?;
+        let output = list.to_string_with_options(&options).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines[3], "   |          11");
+        assert_eq!(lines[4], "   | 12345678901");
+        let column = |line: &str, c| line.chars().position(|lc| lc == c);
+        assert_eq!(column(lines[4], '8'), column(lines[5], 'ö'));
+        assert_eq!(column(lines[6], '^'), column(lines[5], 'ö'));
+        Ok(())
+    }
+
+    #[test]
+    fn test_line_note() -> Result<()> {
+        let mut list = create_list();
+        list.line_note(2, Severity::Warning, "test", "first\nsecond")?;
+        assert_eq!(
+            list.to_string().unwrap(),
+            "warning: test\n  --> test.txt:2\n   |\n 2 | string\n   | first\n   | second\n"
+        );
+        assert!(list.annotations().next().unwrap().is_line_note());
+        assert_eq!(
+            list.line_note(0, Severity::Info, None, None).unwrap_err(),
+            Error::NoSuchLine(0)
+        );
+        assert_eq!(
+            list.line_note(8, Severity::Info, None, None).unwrap_err(),
+            Error::NoSuchLine(8)
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn test_to_bytes_parallel() -> Result<()> {
+        let source = "some line\n".repeat(1000);
+        let mut list = AnnotationList::new("fname", &source);
+        for line in 0..1000 {
+            list.warning(line * 10 + 5..line * 10 + 9, "test", "text")?;
+        }
+        assert_eq!(list.to_bytes_parallel().unwrap(), list.to_bytes().unwrap());
+        assert_eq!(create_list().to_bytes_parallel().unwrap(), b"");
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_source() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "");
+        assert_eq!(list.annotated_lines().len(), 1);
+        assert_start_content(&list.annotated_lines()[0], 0, "");
+        assert_eq!(
+            list.error(0..1, None, None).unwrap_err(),
+            Error::MultilineRange(0, 1)
+        );
+        assert_eq!(
+            list.error(1..1, None, None).unwrap_err(),
+            Error::AfterStringEnd(1, 1)
+        );
+        list.error(0..0, "empty file", None)?;
+        assert_eq!(
+            list.to_string().unwrap(),
+            "error: empty file\n  --> fname:1:1\n   |\n 1 |\n   |\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_trailing_line() -> Result<()> {
+        let source = "line\n";
+        let list = AnnotationList::new("fname", source);
+        assert_eq!(list.annotated_lines().len(), 1);
+        let index = LineIndex::with_trailing_line(source);
+        assert_eq!(index.locate(5), Some((2, 1)));
+        assert_eq!(index.offset_at(2, 1), Some(5));
+        let mut list = AnnotationList::with_index("fname", source, &index);
+        assert_eq!(list.annotated_lines().len(), 2);
+        assert_start_content(&list.annotated_lines()[1], 5, "");
+        list.error(5..5, "eof", None)?;
+        assert!(list
+            .to_string()
+            .unwrap()
+            .contains("--> fname:2:1\n   |\n 2 |\n"));
+        assert_eq!(
+            LineIndex::with_trailing_line("line"),
+            LineIndex::new("line")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_replacement() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "let x = foo;");
+        list.replacement(8..11, "test", "bar")?;
+        assert!(list.to_string().unwrap().ends_with(
+            " 1 | let x = foo;\n   |         ^^^ help: replace with\n   |         bar\n"
+        ));
+        assert_eq!(
+            list.annotations().next().unwrap().replacement(),
+            Some("bar")
+        );
+        let mut list = AnnotationList::new("fname", "let x = foo;");
+        list.replacement(4..5, "test", "value")?;
+        assert!(list
+            .to_string()
+            .unwrap()
+            .ends_with(" 1 | let x = foo;\n   |     ^ help: replace with\n   |     value\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotation_text_impls() -> Result<()> {
+        let expected = Annotation::info(0..1, "header", "text")?;
+        assert_eq!(
+            Annotation::info(0..1, Cow::Borrowed("header"), Cow::from("text".to_string()))?,
+            expected
+        );
+        assert_eq!(
+            Annotation::info(0..1, Box::<str>::from("header"), "text")?,
+            expected
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_located_annotations() -> Result<()> {
+        let mut list = create_list();
+        list.info(1..3, "test1", "ann1")?
+            .warning(13..17, "test2", "ann2")?
+            .error(19..20, "test3", None)?
+            .error(14..16, "test4", "ann4")?
+            .error(14..16, None, "ann5")?;
+        let located: Vec<_> = list
+            .located_annotations()
+            .map(|(line, column, annotation)| (line, column, annotation.header.as_deref()))
+            .collect();
+        assert_eq!(
+            located,
+            [
+                (2, 1, Some("test1")),
+                (4, 1, Some("test2")),
+                (4, 2, Some("test4")),
+                (4, 2, None),
+                (6, 1, Some("test3")),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_underline_span() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "hello world");
+        list.error(6..11, "test", None)?;
+        let stylesheet = Stylesheet::monochrome();
+        let options = RenderOptions {
+            underline_span: true,
+            ..RenderOptions::default()
+        };
+        let output = list
+            .to_ansi_string_with_options(&stylesheet, &options)
+            .unwrap();
+        assert!(!output.contains('^'));
+        assert!(output.ends_with("hello \x1b[0m\x1b[4mworld\n\x1b[0m"));
+        let mut list = AnnotationList::new("fname", "hello world");
+        list.error(6..11, "test", "text")?;
+        let mut output = String::new();
+        list.render_into_with_options(&mut output, &stylesheet, &options);
+        assert!(output.ends_with(" 1 | hello world\n   |       text\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_shared_labels() -> Result<()> {
+        let mut list = create_list();
+        list.define_label("label", "shared text");
+        list.add(Annotation::builder(13..17).label_id("label").build()?)?
+            .add(Annotation::builder(1..3).label_id("label").build()?)?
+            .add(
+                Annotation::builder(8..9)
+                    .label_id("unknown")
+                    .text("own text")
+                    .build()?,
+            )?;
+        let output = list.to_string().unwrap();
+        assert!(output.contains(" 2 | string\n   | ^^ shared text\n"));
+        assert!(output.contains(" 4 | many\n   | ^^^^ shared text (see 2:1)\n"));
+        assert!(output.contains(" 3 | with\n   | ^ own text\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_content_width() {
+        assert_eq!(create_list().max_content_width(), 8);
+        assert_eq!(AnnotationList::new("fname", "").max_content_width(), 0);
+        assert_eq!(
+            AnnotationList::new("fname", "short\nlönger\nmid\n").max_content_width(),
+            6
+        );
+    }
+
+    #[test]
+    fn test_ascii_only() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "let x = 1;\t ");
+        list.warning(10..12, "trailing whitespace", None)?;
+        let mut stylesheet = Stylesheet::monochrome();
+        stylesheet.visible_whitespace = true;
+        stylesheet.ascii_only = true;
+        let mut output = String::new();
+        list.render_into(&mut output, &stylesheet);
+        assert!(output.contains(" 1 | let x = 1;>.\n"));
+        assert!(output.is_ascii());
+        Ok(())
+    }
+
+    #[test]
+    fn test_revalidate() -> Result<()> {
+        let mut list = create_list();
+        list.info(1..3, "test1", None)?
+            .warning(13..17, "test2", None)?;
+        assert_eq!(list.revalidate(&list.source()), Ok(()));
+        assert_eq!(
+            list.revalidate("\nstring\nwith\nma"),
+            Err(Error::MultilineRange(13, 17))
+        );
+        assert_eq!(
+            list.revalidate("\nstring\nwith"),
+            Err(Error::AfterStringEnd(13, 17))
+        );
+        assert_eq!(
+            list.revalidate("\nstring\nwith\nmanä"),
+            Err(Error::NotCharBoundary(13, 17))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_show_color_passthrough() -> Result<()> {
+        let mut list = create_list();
+        list.warning(1..3, "test1", "ann1")?;
+        let stylesheet = Stylesheet::colored();
+        let mut ansi = termcolor::Ansi::new(vec![]);
+        list.show(&mut ansi, &stylesheet).unwrap();
+        let ansi = String::from_utf8(ansi.into_inner()).unwrap();
+        assert_eq!(ansi, list.to_ansi_string(&stylesheet).unwrap());
+        assert!(ansi.contains("\x1b[0m\x1b[1m\x1b[33mwarning: test1"));
+        let mut no_color = termcolor::NoColor::new(vec![]);
+        list.show(&mut no_color, &stylesheet).unwrap();
+        let no_color = String::from_utf8(no_color.into_inner()).unwrap();
+        assert_eq!(no_color, list.to_string().unwrap());
+        for choice in [
+            ColorChoice::Always,
+            ColorChoice::AlwaysAnsi,
+            ColorChoice::Auto,
+        ] {
+            assert_eq!(
+                list.to_bytes_with_color(&stylesheet, choice).unwrap(),
+                ansi.as_bytes()
+            );
+        }
+        assert_eq!(
+            list.to_bytes_with_color(&stylesheet, ColorChoice::Never)
+                .unwrap(),
+            no_color.as_bytes()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_eol_caret() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "let x = 1\nlet y = 2;\n");
+        list.error(9..9, "expected `;`", "add `;` here")?;
+        assert!(list
+            .to_string()
+            .unwrap()
+            .ends_with(" 1 | let x = 1\n   |          ^ add `;` here\n"));
+        let mut list = AnnotationList::new("fname", "let x = 1\n");
+        list.error(0..0, "start of line", "text")?;
+        assert!(list
+            .to_string()
+            .unwrap()
+            .ends_with(" 1 | let x = 1\n   |\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact() -> Result<()> {
+        let mut list = create_list();
+        list.info(1..3, "test1", "ann1")?
+            .error(19..20, None, None)?
+            .line_note(4, Severity::Warning, None, "first\nsecond")?
+            .error(8..9, "header\nwith newline", None)?;
+        assert_eq!(
+            list.to_compact_string().unwrap(),
+            "info: test1 (test.txt:2:1)\nerror: header (test.txt:3:1)\n\
+             warning: first (test.txt:4)\nerror (test.txt:6:1)\n"
+        );
+        let mut buf = termcolor::Ansi::new(vec![]);
+        list.show_compact(&mut buf, &Stylesheet::colored()).unwrap();
+        let output = String::from_utf8(buf.into_inner()).unwrap();
+        assert!(output.starts_with("\x1b[0m\x1b[1minfo: test1\x1b[0m\x1b[1m\x1b[34m ("));
+        Ok(())
+    }
+
+    #[test]
+    fn test_header_separator() -> Result<()> {
+        let mut list = create_list();
+        list.error(1..3, "test1", None)?.error(13..17, None, None)?;
+        let options = RenderOptions {
+            header_separator: " — ".into(),
+            ..RenderOptions::default()
+        };
+        let output = list.to_string_with_options(&options).unwrap();
+        assert!(output.starts_with("error — test1\n"));
+        assert!(output.contains("\nerror —\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotation_mut() -> Result<()> {
+        let mut list = create_list();
+        list.info(13..14, "test1", None)?;
+        let id = list.push(Annotation::warning(14..16, "test2", "before")?)?;
+        assert_eq!(
+            list.annotation(id).unwrap().header.as_deref(),
+            Some("test2")
+        );
+        let mut annotation = list.annotation_mut(id).unwrap();
+        annotation.set_text("after").set_severity(Severity::Error);
+        assert_eq!(
+            annotation.set_range(12..14),
+            Err(Error::MultilineRange(12, 14))
+        );
+        assert_eq!(
+            annotation.set_range(14..19),
+            Err(Error::MultilineRange(14, 19))
+        );
+        annotation.set_range(15..17)?;
+        let output = list.to_string().unwrap();
+        assert!(output.contains("error: test2\n  --> test.txt:4:3\n"));
+        assert!(output.contains("   |   ^^ after\n"));
+        assert!(list
+            .push(Annotation::info(1000..1001, None, None)?)
+            .is_err());
+        assert!(list
+            .annotation(AnnotationId { line: 3, index: 2 })
+            .is_none());
+        // Line end is the start of the next line
+        let mut list = AnnotationList::new("a", "ab\ncd\n");
+        let id = list.push(Annotation::error(0..1, "test", None)?)?;
+        let mut annotation = list.annotation_mut(id).unwrap();
+        assert_eq!(annotation.set_range(3..3), Err(Error::MultilineRange(3, 3)));
+        annotation.set_range(2..2)?;
+        assert!(list.to_string().unwrap().contains("--> a:1:3\n"));
+        // ...or the end of the source
+        let mut list = AnnotationList::new("a", "ab");
+        let id = list.push(Annotation::error(0..1, "test", None)?)?;
+        let mut annotation = list.annotation_mut(id).unwrap();
+        assert_eq!(annotation.set_range(2..2), Err(Error::MultilineRange(2, 2)));
+        assert_eq!(annotation.range(), &(0..1));
+        assert!(list.error(2..2, "test", None).is_err());
+        // Zero-length annotation is allowed in an empty source
+        let mut list = AnnotationList::new("a", "");
+        let id = list.push(Annotation::error(0..0, "test", None)?)?;
+        list.annotation_mut(id).unwrap().set_range(0..0)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotation_mut_multiline() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "a {\nb\n}\nc\n");
+        let id = list.push_multiline(Annotation::error(2..7, "unclosed", None)?)?;
+        let mut annotation = list.annotation_mut(id).unwrap();
+        assert_eq!(
+            annotation.set_range(2..10),
+            Err(Error::MultilineRange(2, 10))
+        );
+        annotation.set_range(0..7)?;
+        assert_eq!(
+            list.to_string().unwrap(),
+            r#"error: unclosed
+  --> fname:1:1
+   |
+ 1 |   a {
+   |  _^
+ 2 | | b
+ 3 | | }
+   | |_^
+"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_default_color_choice() {
+        assert_eq!(default_color_choice(), None);
+        set_default_color_choice(Some(ColorChoice::Never));
+        assert_eq!(color::resolve_color_choice(None, true), ColorChoice::Never);
+        assert_eq!(
+            color::resolve_color_choice(Some(ColorChoice::Always), true),
+            ColorChoice::Always
+        );
+        set_default_color_choice(None);
+        assert_eq!(default_color_choice(), None);
+        assert_eq!(color::resolve_color_choice(None, false), ColorChoice::Never);
+    }
+
+    #[test]
+    fn test_bold_caret_edges() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "hello world");
+        list.error(6..11, "test", None)?;
+        let mut stylesheet = Stylesheet::monochrome();
+        stylesheet.bold_caret_edges = true;
+        let output = list.to_ansi_string(&stylesheet).unwrap();
+        assert!(output.contains("\x1b[0m\x1b[1m^\x1b[0m^^^\x1b[0m\x1b[1m^\n"));
+        assert_eq!(list.to_string().unwrap(), {
+            let mut plain = String::new();
+            list.render_into(&mut plain, &stylesheet);
+            plain
+        });
+        let mut list = AnnotationList::new("fname", "hello world");
+        list.error(6..7, "test", None)?;
+        let events = list.render_events(&stylesheet);
+        let carets: Vec<_> = events
+            .iter()
+            .flat_map(RenderEvent::segments)
+            .filter(|segment| segment.text == "^")
+            .collect();
+        assert_eq!(carets.len(), 1);
+        assert_eq!(carets[0].role, StyleRole::CaretEdge(Severity::Error));
+        Ok(())
+    }
+
+    #[test]
+    fn test_anonymous() -> Result<()> {
+        let mut list = AnnotationList::anonymous("let x = ;");
+        list.error(8..9, "expected expression", None)?;
+        assert_eq!(
+            list.to_string().unwrap(),
+            "error: expected expression\n  --> <input>:1:9\n   |\n 1 | let x = ;\n   |         ^\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_promote_warnings_to_errors() -> Result<()> {
+        let mut list = create_list();
+        list.warning(1..7, "test1", None)?
+            .info(8..12, "test2", None)?
+            .warning(13..17, "test3", None)?;
+        assert_eq!(list.max_severity(), Some(Severity::Warning));
+        assert_eq!(list.promote_warnings_to_errors(), 2);
+        assert_eq!(list.max_severity(), Some(Severity::Error));
+        let output = list.to_string().unwrap();
+        assert!(output.contains("error: test1\n"));
+        assert!(output.contains("info: test2\n"));
+        assert!(output.contains("error: test3\n"));
+        assert!(!output.contains("warning"));
+        assert_eq!(list.promote_warnings_to_errors(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_tab_width() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "\tx =\t1");
+        list.error(5..6, "test", None)?;
+        let options = RenderOptions {
+            tab_width: Some(4),
+            ..RenderOptions::default()
+        };
+        let mut stylesheet = Stylesheet::monochrome();
+        let render = |stylesheet: &Stylesheet| {
+            let mut buf = termcolor::Buffer::no_color();
+            list.show_with_options(&mut buf, stylesheet, &options)
+                .unwrap();
+            String::from_utf8(buf.into_inner()).unwrap()
+        };
+        assert!(render(&stylesheet).ends_with(" 1 |     x =    1\n   |            ^\n"));
+        assert!(list
+            .to_string()
+            .unwrap()
+            .ends_with(" 1 | \tx =\t1\n   |      ^\n"));
+        let mut list = AnnotationList::new("fname", "x\t\t");
+        list.warning(1..3, "trailing whitespace", None)?;
+        stylesheet.visible_whitespace = true;
+        stylesheet.ascii_only = true;
+        let mut buf = termcolor::Buffer::no_color();
+        list.show_with_options(&mut buf, &stylesheet, &options)
+            .unwrap();
+        let output = String::from_utf8(buf.into_inner()).unwrap();
+        assert!(output.ends_with(" 1 | x>   >   \n   |  ^^^^^^^^\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_line_char_len() {
+        let list = AnnotationList::new("fname", "café crème\n\nnaïve");
+        assert_eq!(list.line_char_len(1), Some(10));
+        assert_eq!(list.line_char_len(2), Some(0));
+        assert_eq!(list.line_char_len(3), Some(5));
+        assert_eq!(list.line_char_len(0), None);
+        assert_eq!(list.line_char_len(4), None);
+    }
+
+    #[test]
+    fn test_succinct_full_line() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "first\nunreachable();\n");
+        list.warning(6..20, "unreachable statement", "never executed")?;
+        assert!(list
+            .to_string()
+            .unwrap()
+            .ends_with(" 2 | unreachable();\n   | ^^^^^^^^^^^^^^ never executed\n"));
+        let mut options = RenderOptions {
+            succinct_full_line: true,
+            ..RenderOptions::default()
+        };
+        let mut stylesheet = Stylesheet::monochrome();
+        let render = |stylesheet: &Stylesheet, options: &RenderOptions| {
+            let mut buf = termcolor::Buffer::no_color();
+            list.show_with_options(&mut buf, stylesheet, options)
+                .unwrap();
+            String::from_utf8(buf.into_inner()).unwrap()
+        };
+        assert!(render(&stylesheet, &options)
+            .ends_with(" 2 | unreachable();\n   | ^… never executed\n"));
+        stylesheet.ascii_only = true;
+        assert!(render(&stylesheet, &options)
+            .ends_with(" 2 | unreachable();\n   | ^... never executed\n"));
+        options.succinct_full_line = false;
+        assert_eq!(render(&stylesheet, &options), list.to_string().unwrap());
+        let mut list = AnnotationList::new("fname", "unreachable();");
+        list.warning(0..11, "partial", None)?;
+        let mut buf = termcolor::Buffer::no_color();
+        options.succinct_full_line = true;
+        list.show_with_options(&mut buf, &stylesheet, &options)
+            .unwrap();
+        assert!(String::from_utf8(buf.into_inner())
+            .unwrap()
+            .ends_with("   | ^^^^^^^^^^^\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_multiline_annotation() -> Result<()> {
+        let source = "1\n2\n3\n4\n5\n6\n7\n8\nfn f() {\n    x\n";
+        let mut list = AnnotationList::new("fname", source);
+        assert_eq!(
+            list.add(Annotation::error(16..30, "test", None)?)
+                .unwrap_err()
+                .error,
+            Error::MultilineRange(16, 30)
+        );
+        list.add_multiline(Annotation::error(16..30, "test", "first\nsecond")?)?;
+        assert_eq!(
+            list.to_string().unwrap(),
+            r#"error: test
+   --> fname:9:1
+    |
+  9 |   fn f() {
+    |  _^
+ 10 | |     x
+    | |_____^ first
+    |         second
+"#
+        );
+        assert_eq!(list.revalidate(source), Ok(()));
+        assert_eq!(
+            list.revalidate("1\n2\n3\n4\n5\n6\n7\n8\nfn f() {"),
+            Err(Error::AfterStringEnd(16, 30))
+        );
+        assert_eq!(
+            list.add_multiline(Annotation::info(20..100, None, None)?)
+                .unwrap_err()
+                .error,
+            Error::AfterStringEnd(20, 100)
+        );
+        #[cfg(feature = "sarif")]
+        assert!(list
+            .to_sarif()
+            .contains(r#""startLine":9,"startColumn":1,"endLine":10,"endColumn":6"#));
+        let mut single = AnnotationList::new("fname", source);
+        single.add_multiline(Annotation::error(16..18, "test", None)?)?;
+        let mut expected = AnnotationList::new("fname", source);
+        expected.error(16..18, "test", None)?;
+        assert_eq!(single.to_string().unwrap(), expected.to_string().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_multiline_multibyte_end() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "s = \"a\nbé\";\n");
+        list.add_multiline(Annotation::error(4..10, "test", "here")?)?;
+        let expected = r#"error: test
+  --> fname:1:5
+   |
+ 1 |   s = "a
+   |  _____^
+ 2 | | bé";
+   | |__^ here
+"#;
+        assert_eq!(list.to_string().unwrap(), expected);
+        let options = RenderOptions {
+            char_width: char::len_utf8,
+            ..RenderOptions::default()
+        };
+        assert_eq!(list.to_string_with_options(&options).unwrap(), expected);
+        Ok(())
+    }
+
+    #[test]
+    #[allow(clippy::reversed_empty_ranges)]
+    fn test_secondary_spans() -> Result<()> {
+        let source = "fn f(a: &mut T) {\n    g(a, a);\n}\n1\n2\n3\n4\n5\n6\n// end\n";
+        let mut list = AnnotationList::new("fname", source);
+        list.add(
+            Annotation::builder(27..28)
+                .header("second mutable borrow")
+                .text("second borrow")
+                .secondary(24..25, "first borrow")
+                .secondary(5..6, "declared here")
+                .secondary(45..51, "unrelated\nline")
+                .build()?,
+        )?;
+        assert_eq!(
+            list.to_string().unwrap(),
+            r#"error: second mutable borrow
+   --> fname:2:10
+    |
+  1 | fn f(a: &mut T) {
+    |      - declared here
+  2 |     g(a, a);
+    |          ^ second borrow
+    |       - first borrow
+ 10 | // end
+    | ------ unrelated
+    |        line
+"#
+        );
+        let mut annotation = Annotation::error(0..1, None, None)?;
+        annotation.add_secondary(16..20, None)?;
+        assert_eq!(
+            list.add(annotation.clone()).unwrap_err().error,
+            Error::MultilineRange(16, 20)
+        );
+        annotation.secondary.clear();
+        annotation.add_secondary(100..101, None)?;
+        assert_eq!(
+            list.add(annotation).unwrap_err().error,
+            Error::AfterStringEnd(100, 101)
+        );
+        assert_eq!(
+            Annotation::builder(0..1).secondary(3..2, None).build(),
+            Err(Error::InvalidRange(3, 2))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_notes() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "let x = 1;\nx = 2;\n");
+        list.add(
+            Annotation::builder(11..16)
+                .header("cannot assign twice to immutable variable")
+                .note("variables are immutable by default")
+                .help("consider making the binding mutable:\nlet mut x = 1;")
+                .build()?,
+        )?;
+        let expected = r#"error: cannot assign twice to immutable variable
+  --> fname:2:1
+   |
+ 2 | x = 2;
+   | ^^^^^
+   |
+   = note: variables are immutable by default
+   = help: consider making the binding mutable:
+           let mut x = 1;
+"#;
+        assert_eq!(list.to_string().unwrap(), expected);
+        let mut stylesheet = Stylesheet::monochrome();
+        stylesheet.labels.help = "aide".into();
+        let mut output = String::new();
+        list.render_into(&mut output, &stylesheet);
+        assert!(output.contains("   = aide: consider making the binding mutable:\n           let"));
+        assert_eq!(list.annotations().next().unwrap().notes().len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_suggestions() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "let x = foo(1 2);\n");
+        list.add(
+            Annotation::builder(13..14)
+                .header("expected `,`")
+                .suggestion(13..13, ",")
+                .build()?,
+        )?
+        .add({
+            let mut annotation = Annotation::warning(4..5, "unused variable", None)?;
+            annotation
+                .add_suggestion(4..5, "_x")?
+                .add_suggestion(0..8, "")?
+                .add_suggestion(16..17, ";\nfoo(3)")?;
+            annotation
+        })?;
+        assert_eq!(
+            list.to_string().unwrap(),
+            r#"error: expected `,`
+  --> fname:1:14
+   |
+ 1 | let x = foo(1 2);
+   |              ^
+help: insert `,`
+   |
+ 1 | let x = foo(1, 2);
+   |              +
+
+warning: unused variable
+  --> fname:1:5
+   |
+ 1 | let x = foo(1 2);
+   |     ^
+help: replace with `_x`
+   |
+ 1 | let _x = foo(1 2);
+   |     ~~
+help: remove `let x = `
+   |
+ 1 | let x = foo(1 2);
+   | --------
+help: replace with
+   |
+ 1 | let x = foo(1 2);
+   |                 ~
+ 2 | foo(3)
+   | ~~~~~~
+"#
+        );
+        assert_eq!(
+            list.add(Annotation::builder(0..1).suggestion(0..100, "").build()?)
+                .unwrap_err()
+                .error,
+            Error::MultilineRange(0, 100)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_suggestions() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "fn f(a: i32) {\n    return a\n}\n");
+        assert_eq!(list.apply_suggestions(), list.source());
+        list.add(
+            Annotation::builder(19..25)
+                .suggestion(19..26, "")
+                .suggestion(27..27, ";")
+                .build()?,
+        )?
+        .add(
+            Annotation::builder(19..25)
+                .suggestion(19..25, "ret")
+                .build()?,
+        )?
+        .add(
+            Annotation::builder(8..11)
+                .suggestion(8..11, "u32")
+                .build()?,
+        )?
+        .add(
+            Annotation::builder(27..28)
+                .suggestion(28..28, "\n")
+                .build()?,
+        )?;
+        assert_eq!(
+            list.apply_suggestions(),
+            "fn f(a: u32) {\n    ret a;\n\n}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_context_lines() -> Result<()> {
+        let source = "[server]\nhost = \"localhost\"\nport = \"8080\"\n\n[client]\nretries = 3\n";
+        let mut list = AnnotationList::new("config.toml", source);
+        list.error(35..41, "expected integer", None)?.add(
+            Annotation::builder(52..63)
+                .secondary(0..8, "in this section")
+                .build()?,
+        )?;
+        let options = RenderOptions {
+            context_lines: 2,
+            ..RenderOptions::default()
+        };
+        let mut buf = termcolor::Buffer::no_color();
+        list.show_with_options(&mut buf, &Stylesheet::monochrome(), &options)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(buf.into_inner()).unwrap(),
+            r#"error: expected integer
+  --> config.toml:3:8
+   |
+ 1 | [server]
+ 2 | host = "localhost"
+ 3 | port = "8080"
+   |        ^^^^^^
+ 4 |
+ 5 | [client]
+
+error:
+  --> config.toml:6:1
+   |
+ 1 | [server]
+   | -------- in this section
+ 4 |
+ 5 | [client]
+ 6 | retries = 3
+   | ^^^^^^^^^^^
+"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_summary() -> Result<()> {
+        let mut list = AnnotationList::new("hello.txt", "Hello world!");
+        list.warning(4..7, "punctuation problem", None)?;
+        let mut options = RenderOptions {
+            summary: true,
+            ..RenderOptions::default()
+        };
+        let stylesheet = Stylesheet::monochrome();
+        let render = |list: &AnnotationList, options: &RenderOptions| {
+            let mut buf = termcolor::Buffer::no_color();
+            list.show_with_options(&mut buf, &stylesheet, options)
+                .unwrap();
+            String::from_utf8(buf.into_inner()).unwrap()
+        };
+        assert_eq!(
+            render(&list, &options),
+            r#"warning: punctuation problem
+  --> hello.txt:1:5
+   |
+ 1 | Hello world!
+   |     ^^^
+
+warning: 1 warning emitted
+"#
+        );
+        options.separator_lines = 0;
+        let mut other = AnnotationList::new("other.txt", "Hi");
+        other.error(0..2, "unknown word", None)?;
+        other.info(0..0, "consider adding a comma", None)?;
+        let report: MultiFileReport = vec![list, other].into_iter().collect();
+        let counts = report.severity_counts();
+        assert_eq!((counts.errors, counts.warnings, counts.infos), (1, 1, 1));
+        let mut buf = termcolor::Buffer::no_color();
+        report
+            .show_with_options(&mut buf, &stylesheet, &options)
+            .unwrap();
+        let output = String::from_utf8(buf.into_inner()).unwrap();
+        assert!(output.ends_with("\nerror: aborting due to 1 previous error; 1 warning emitted\n"));
+        assert_eq!(output.matches("emitted").count(), 1);
+
+        let mut stylesheet = Stylesheet::monochrome();
+        stylesheet.labels.warnings_summary = "{count} avertissement{s}".into();
+        let mut infos_only = AnnotationList::new("hello.txt", "Hello world!");
+        infos_only.info(0..5, "greeting", None)?;
+        assert_eq!(
+            infos_only.severity_counts().summary(&stylesheet.labels),
+            None
+        );
+        infos_only
+            .warning(5..6, "missing comma", None)?
+            .warning(11..12, "too excited", None)?;
+        assert_eq!(
+            infos_only.severity_counts().summary(&stylesheet.labels),
+            Some((Severity::Warning, "2 avertissements".into()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_note_help_severities() -> Result<()> {
+        let mut list = AnnotationList::new("hello.txt", "Hello world!");
+        list.note(0..5, "greeting", "first word")?
+            .help(6..11, None, "consider `Earth`")?;
+        assert_eq!(list.max_severity(), Some(Severity::Note));
+        assert_eq!(list.suggested_exit_code(), 0);
+        let counts = list.severity_counts();
+        assert_eq!((counts.notes, counts.helps, counts.infos), (1, 1, 0));
+        assert_eq!(
+            list.to_string().unwrap(),
+            r#"note: greeting
+  --> hello.txt:1:1
+   |
+ 1 | Hello world!
+   | ^^^^^ first word
+
+help:
+  --> hello.txt:1:7
+   |
+ 1 | Hello world!
+   |       ^^^^^ consider `Earth`
+"#
+        );
+
+        let stylesheet = Stylesheet::colored();
+        let colored = list.to_ansi_string(&stylesheet).unwrap();
+        assert!(colored.contains(&format!("{}note", ansi(&stylesheet.note))));
+        assert!(colored.contains(&format!("{}help", ansi(&stylesheet.help))));
+        assert!(Severity::Help < Severity::Note && Severity::Note < Severity::Info);
+        Ok(())
+    }
+
+    #[test]
+    fn test_custom_severity() -> Result<()> {
+        const DEPRECATION: Severity = Severity::Custom {
+            name: "deprecation",
+            rank: 350,
+        };
+        const FATAL: Severity = Severity::Custom {
+            name: "fatal",
+            rank: 600,
+        };
+        let mut list = AnnotationList::new("hello.txt", "Hello world!");
+        list.add(Annotation::new(
+            0..5,
+            DEPRECATION,
+            "old greeting",
+            "use `Hi`",
+        )?)?;
+        assert_eq!(list.max_severity(), Some(DEPRECATION));
+        assert_eq!(list.suggested_exit_code(), 0);
+        assert_eq!(
+            list.to_string().unwrap(),
+            r#"deprecation: old greeting
+  --> hello.txt:1:1
+   |
+ 1 | Hello world!
+   | ^^^^^ use `Hi`
+"#
+        );
+        let mut stylesheet = Stylesheet::colored();
+        let colored = list.to_ansi_string(&stylesheet).unwrap();
+        assert!(colored.contains(&format!("{}deprecation", ansi(&stylesheet.info))));
+        let mut spec = termcolor::ColorSpec::new();
+        spec.set_fg(Some(termcolor::Color::Magenta));
+        stylesheet.custom.insert("deprecation".into(), spec.clone());
+        let colored = list.to_ansi_string(&stylesheet).unwrap();
+        assert!(colored.contains(&format!("{}deprecation", ansi(&spec))));
+
+        list.add(Annotation::new(6..11, FATAL, None, None)?)?;
+        assert_eq!(list.max_severity(), Some(FATAL));
+        assert_eq!(list.suggested_exit_code(), 1);
+        assert_eq!(list.severity_counts().custom, 2);
+        assert_eq!(FATAL.rank(), 600);
+        assert!(Severity::Info < DEPRECATION && DEPRECATION < Severity::Warning);
+        let impostor = Severity::Custom {
+            name: "error",
+            rank: 500,
+        };
+        assert_ne!(impostor.cmp(&Severity::Error), cmp::Ordering::Equal);
+        assert!("deprecation".parse::<Severity>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_error_codes() -> Result<()> {
+        let mut list = AnnotationList::new("main.rs", "let x: i32 = \"1\";");
+        list.add(
+            Annotation::builder(13..16)
+                .header("mismatched types")
+                .text("expected `i32`, found `&str`")
+                .code("E0308")
+                .build()?,
+        )?
+        .add(
+            Annotation::builder(4..5)
+                .severity(Severity::Warning)
+                .code("unused_variables")
+                .build()?,
+        )?;
+        assert_eq!(
+            list.to_string().unwrap(),
+            r#"error[E0308]: mismatched types
+  --> main.rs:1:14
+   |
+ 1 | let x: i32 = "1";
+   |              ^^^ expected `i32`, found `&str`
+
+warning[unused_variables]:
+  --> main.rs:1:5
+   |
+ 1 | let x: i32 = "1";
+   |     ^
+"#
+        );
+        assert_eq!(
+            list.to_compact_string().unwrap(),
+            "error[E0308]: mismatched types (main.rs:1:14)\nwarning[unused_variables] (main.rs:1:5)\n"
+        );
+
+        let mut stylesheet = Stylesheet::colored();
+        let colored = list.to_ansi_string(&stylesheet).unwrap();
+        assert!(colored.contains(&format!("{}E0308", ansi(&stylesheet.error))));
+        let mut code = termcolor::ColorSpec::new();
+        code.set_fg(Some(termcolor::Color::Cyan));
+        stylesheet.code = Some(code.clone());
+        let colored = list.to_ansi_string(&stylesheet).unwrap();
+        assert!(colored.contains(&format!("{}E0308", ansi(&code))));
+
+        #[cfg(feature = "sarif")]
+        assert!(list.to_sarif().contains(
+            r#"{"ruleId":"E0308","level":"error","message":{"text":"mismatched types"}"#
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_url() -> Result<()> {
+        let url = "https://example.com/lints/unused";
+        let mut list = AnnotationList::new("main.rs", "let x = 1;");
+        list.add(
+            Annotation::builder(4..5)
+                .severity(Severity::Warning)
+                .header("unused variable")
+                .help("prefix it with an underscore")
+                .url(url)
+                .build()?,
+        )?;
+        assert_eq!(
+            list.to_string().unwrap(),
+            r#"warning: unused variable
+  --> main.rs:1:5
+   |
+ 1 | let x = 1;
+   |     ^
+   |
+   = help: prefix it with an underscore
+   = note: see https://example.com/lints/unused
+"#
+        );
+        let link = format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, url);
+        let mut stylesheet = Stylesheet::colored();
+        assert!(!list.to_ansi_string(&stylesheet).unwrap().contains(&link));
+        stylesheet.hyperlinks = true;
+        assert!(list.to_ansi_string(&stylesheet).unwrap().contains(&link));
+        let mut buf = termcolor::Buffer::no_color();
+        list.show(&mut buf, &stylesheet).unwrap();
+        assert!(!String::from_utf8(buf.into_inner())
+            .unwrap()
+            .contains('\x1b'));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_to_json() -> Result<()> {
+        let mut list = AnnotationList::new("src/\"quoted\".rs", "let é = 1;\nlet y = é;\n");
+        list.add(
+            Annotation::builder(16..17)
+                .severity(Severity::Warning)
+                .header("unused variable")
+                .text("unused")
+                .secondary(4..6, None)
+                .note("defined here")
+                .suggestion(16..17, "_y")
+                .build()?,
+        )?;
+        let json = list.to_json();
+        assert_eq!(json.lines().count(), 1);
+        assert_eq!(
+            json,
+            concat!(
+                r#"{"$message_type":"diagnostic","message":"unused variable","code":null,"#,
+                r#""level":"warning","spans":["#,
+                r#"{"file_name":"src/\"quoted\".rs","byte_start":16,"byte_end":17,"#,
+                r#""line_start":2,"line_end":2,"column_start":5,"column_end":6,"is_primary":true,"#,
+                r#""text":[{"text":"let y = é;","highlight_start":5,"highlight_end":6}],"#,
+                r#""label":"unused","suggested_replacement":null,"#,
+                r#""suggestion_applicability":null,"expansion":null},"#,
+                r#"{"file_name":"src/\"quoted\".rs","byte_start":4,"byte_end":6,"#,
+                r#""line_start":1,"line_end":1,"column_start":5,"column_end":6,"is_primary":false,"#,
+                r#""text":[{"text":"let é = 1;","highlight_start":5,"highlight_end":6}],"#,
+                r#""label":null,"suggested_replacement":null,"#,
+                r#""suggestion_applicability":null,"expansion":null}],"#,
+                r#""children":[{"message":"defined here","code":null,"level":"note","#,
+                r#""spans":[],"children":[],"rendered":null},"#,
+                r#"{"message":"replace with `_y`","code":null,"level":"help","spans":["#,
+                r#"{"file_name":"src/\"quoted\".rs","byte_start":16,"byte_end":17,"#,
+                r#""line_start":2,"line_end":2,"column_start":5,"column_end":6,"is_primary":true,"#,
+                r#""text":[{"text":"let y = é;","highlight_start":5,"highlight_end":6}],"#,
+                r#""label":null,"suggested_replacement":"_y","#,
+                r#""suggestion_applicability":"MaybeIncorrect","expansion":null}],"#,
+                r#""children":[],"rendered":null}],"rendered":"#,
+            )
+            .to_owned()
+                + &format!(
+                    "\"{}\"}}\n",
+                    list.to_string().unwrap().replace('"', "\\\"").replace('\n', "\\n")
+                )
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "sarif")]
+    fn test_report_to_sarif() -> Result<()> {
+        let mut main = AnnotationList::new("src\\main.rs", "fn main() {}");
+        main.add(
+            Annotation::builder(3..7)
+                .severity(Severity::Warning)
+                .header("function is never used")
+                .code("dead_code")
+                .build()?,
+        )?;
+        let mut lib = AnnotationList::new("src/lib.rs", "mod foo;\nmod bar;");
+        lib.add(Annotation::builder(4..7).code("E0583").build()?)?
+            .add(
+                Annotation::builder(13..16)
+                    .code("E0583")
+                    .url("https://example.com/E0583")
+                    .build()?,
+            )?;
+        let report: MultiFileReport = vec![main, lib].into_iter().collect();
+        let sarif = report.to_sarif();
+        assert!(sarif.contains(concat!(
+            r#""driver":{"name":"show-my-errors","rules":["#,
+            r#"{"id":"E0583","helpUri":"https://example.com/E0583"},{"id":"dead_code"}]}"#,
+        )));
+        assert_eq!(sarif.matches(r#""ruleId":"E0583""#).count(), 2);
+        let lib_pos = sarif.find(r#""uri":"src/lib.rs""#).unwrap();
+        assert!(lib_pos < sarif.find(r#""uri":"src/main.rs""#).unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_github_actions() -> Result<()> {
+        let mut list = AnnotationList::new("a,b.txt", "héllo\nworld\n");
+        list.add(
+            Annotation::builder(1..3)
+                .header("100% wrong\naccent")
+                .code("x:1")
+                .build()?,
+        )?
+        .add_multiline(Annotation::warning(3..10, None, "spans lines")?)?
+        .line_note(2, Severity::Info, "second line", None)?;
+        let report: MultiFileReport = vec![list, AnnotationList::new("0.txt", "")]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            report.to_github_actions(),
+            concat!(
+                "::error file=a%2Cb.txt,line=1,col=2,endColumn=3,title=x%3A1::100%25 wrong%0Aaccent\n",
+                "::warning file=a%2Cb.txt,line=1,col=3,endLine=2::spans lines\n",
+                "::notice file=a%2Cb.txt,line=2::second line\n",
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_code_quality_json() -> Result<()> {
+        let source = "x = 1\nx = 1\n";
+        let mut list = AnnotationList::new("src\\conf.py", source);
+        list.add(
+            Annotation::builder(0..1)
+                .header("unused")
+                .code("W0612")
+                .build()?,
+        )?
+        .add(
+            Annotation::builder(6..7)
+                .header("unused")
+                .code("W0612")
+                .build()?,
+        )?
+        .info(4..5, "literal", None)?;
+        let report = list.to_code_quality_json();
+        let fingerprints: Vec<_> = report
+            .split(r#""fingerprint":""#)
+            .skip(1)
+            .map(|rest| &rest[..20])
+            .collect();
+        assert_eq!(fingerprints.len(), 3);
+        // Info on line 1 goes before the same issue on line 2
+        assert_eq!(fingerprints[0][..16], fingerprints[2][..16]);
+        assert_ne!(fingerprints[0], fingerprints[2]);
+        let location = r#""location":{"path":"src/conf.py","lines":{"begin":2,"end":2}}}"#;
+        assert!(report.contains(&format!(r#""severity":"major",{}"#, location)));
+        assert!(report.contains(r#"{"description":"literal","check_name":"info","#));
+
+        // Fingerprints don't depend on line numbers
+        let moved = format!("\n{}", source);
+        let mut other = AnnotationList::new("src\\conf.py", &moved);
+        other.add(
+            Annotation::builder(1..2)
+                .header("unused")
+                .code("W0612")
+                .build()?,
+        )?;
+        assert!(other.to_code_quality_json().contains(fingerprints[0]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_checkstyle_xml() -> Result<()> {
+        let mut first = AnnotationList::new("b.rs", "let x = \"<&>\";");
+        first.add(
+            Annotation::builder(8..13)
+                .header("string \"<&>\"")
+                .code("E'1")
+                .build()?,
+        )?;
+        let mut second = AnnotationList::new("b.rs", "fn main() {}");
+        second.line_note(1, Severity::Info, "entry point", None)?;
+        let mut third = AnnotationList::new("a.rs", "");
+        third.info(0..0, None, None)?;
+        let report: MultiFileReport = vec![first, second, third].into_iter().collect();
+        assert_eq!(
+            report.to_checkstyle_xml(),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<checkstyle version="4.3">
+<file name="a.rs">
+<error line="1" column="1" severity="info" message=""/>
+</file>
+<file name="b.rs">
+<error line="1" column="9" severity="error" message="string &quot;&lt;&amp;&gt;&quot;" source="E&apos;1"/>
+<error line="1" severity="info" message="entry point"/>
+</file>
+</checkstyle>
+"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_html() -> Result<()> {
+        let mut list = AnnotationList::new("a&b.rs", "if a < b {}");
+        list.add(
+            Annotation::builder(5..6)
+                .severity(Severity::Warning)
+                .code("W1")
+                .text("here")
+                .url("https://example.com/?a=1&b=2")
+                .build()?,
+        )?;
+        assert_eq!(
+            list.to_html(),
+            concat!(
+                r#"<pre class="smy-diagnostic"><span class="smy-warning">warning[</span>"#,
+                r#"<span class="smy-warning smy-code">W1</span><span class="smy-warning">]:</span>"#,
+                "\n",
+                r#"<span class="smy-line-number">  --&gt; </span><span class="smy-filename">a&amp;b.rs:1:6</span>"#,
+                "\n",
+                r#"<span class="smy-line-number">   |</span>"#,
+                "\n",
+                r#"<span class="smy-line-number"> 1 | </span><span class="smy-content">if a &lt; b {}</span>"#,
+                "\n",
+                r#"<span class="smy-line-number">   |</span><span class="smy-warning">      ^ here</span>"#,
+                "\n",
+                r#"<span class="smy-line-number">   |</span>"#,
+                "\n",
+                r#"<span class="smy-line-number">   = </span><span class="smy-note">note: </span>"#,
+                r#"<span class="smy-content">see </span>"#,
+                r#"<a class="smy-link" href="https://example.com/?a=1&amp;b=2">https://example.com/?a=1&amp;b=2</a>"#,
+                "</pre>\n",
+            )
+        );
+
+        let mut list = AnnotationList::new("a.rs", "x");
+        list.add(
+            Annotation::builder(0..1)
+                .severity(Severity::Custom {
+                    name: "a\" onclick=\"alert(1)",
+                    rank: 300,
+                })
+                .url("javascript:alert(1)")
+                .build()?,
+        )?;
+        let html = list.to_html();
+        assert!(html.starts_with(
+            r#"<pre class="smy-diagnostic"><span class="smy-a&quot;-onclick=&quot;alert(1)">"#
+        ));
+        assert!(!html.contains("href"));
+        assert!(html.contains(r#"<span class="smy-link">javascript:alert(1)</span>"#));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "svg")]
+    fn test_to_svg() -> Result<()> {
+        let mut list = AnnotationList::new("a.rs", "x < y");
+        list.error(2..3, None, None)?;
+        let mut stylesheet = Stylesheet::monochrome();
+        stylesheet
+            .linenr
+            .set_fg(Some(termcolor::Color::Ansi256(196)));
+        stylesheet
+            .filename
+            .set_fg(Some(termcolor::Color::Rgb(1, 2, 3)));
+        stylesheet.error.set_italic(true).set_dimmed(true);
+        let svg = list.to_svg(&stylesheet);
+        // 5 lines, the longest one is `  --> a.rs:1:3` of 14 columns
+        assert!(svg.contains(r#"width="137.6" height="111.0""#));
+        assert_eq!(svg.matches("<text ").count(), 5);
+        assert!(svg.contains(r##"<tspan fill="#ff0000">  --&gt; </tspan>"##));
+        assert!(svg.contains(r##"<tspan fill="#010203">a.rs:1:3</tspan>"##));
+        assert!(svg.contains(r#"<tspan>x &lt; y</tspan>"#));
+        assert!(svg.contains(r#"<tspan font-style="italic" opacity="0.7">   ^</tspan>"#));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "tui")]
+    fn test_to_tui_lines() -> Result<()> {
+        use crate::tui::{Color, Modifier, Span, Style};
+        let mut list = AnnotationList::new("a.rs", "x < y");
+        list.error(2..3, None, None)?;
+        let mut stylesheet = Stylesheet::monochrome();
+        stylesheet
+            .linenr
+            .set_fg(Some(termcolor::Color::Ansi256(196)));
+        stylesheet
+            .error
+            .set_fg(Some(termcolor::Color::Red))
+            .set_intense(true)
+            .set_italic(true);
+        let lines = list.to_tui_lines(&stylesheet);
+        assert_eq!(lines.len(), 5);
+        let linenr = Style {
+            fg: Some(Color::Indexed(196)),
+            ..Style::default()
+        };
+        let error = Style {
+            fg: Some(Color::LightRed),
+            add_modifier: Modifier {
+                italic: true,
+                ..Modifier::default()
+            },
+            ..Style::default()
+        };
+        let span = |content: &str, style| Span {
+            content: content.into(),
+            style,
+        };
+        assert_eq!(lines[0].spans, [span("error:", error)]);
+        assert_eq!(
+            lines[3].spans,
+            [span(" 1 | ", linenr), span("x < y", Style::default())]
+        );
+        assert_eq!(lines[4].spans, [span("   |", linenr), span("   ^", error)]);
+        stylesheet.error.set_bold(true).set_intense(false);
+        let lines = list.to_tui_lines(&stylesheet);
+        assert_eq!(lines[0].spans[0].style.fg, Some(Color::Red));
+        assert!(lines[0].spans[0].style.add_modifier.bold);
+        Ok(())
+    }
+
+    #[test]
+    fn test_short() -> Result<()> {
+        let mut list = AnnotationList::new("dir\\hello.txt", "Hello world!\nsecond line\n");
+        list.add(
+            Annotation::builder(4..7)
+                .severity(Severity::Warning)
+                .header("punctuation problem")
+                .code("W0001")
+                .build()?,
+        )?
+        .info(13..19, None, "multi-line\ntext")?
+        .line_note(2, Severity::Note, "line note", None)?
+        .error(20..21, "multi-line\nheader", "text")?;
+        let stylesheet = Stylesheet::monochrome();
+        let options = RenderOptions {
+            normalize_separators: true,
+            ..RenderOptions::default()
+        };
+        let mut buf = String::new();
+        write_events(
+            &mut buf,
+            &stylesheet,
+            &options,
+            &list.short_events(&stylesheet, &options),
+        )
+        .unwrap();
+        assert_eq!(
+            buf,
+            "dir/hello.txt:1:5: warning[W0001]: punctuation problem\n\
+             dir/hello.txt:2:1: info: multi-line\n\
+             dir/hello.txt:2: note: line note\n\
+             dir/hello.txt:2:8: error: multi-line\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_quickfix() -> Result<()> {
+        let mut list = AnnotationList::new("b.rs", "let x = 1;\nlet y = x;\n");
+        list.line_note(2, Severity::Note, None, "no column")?
+            .info(15..16, "same place", None)?
+            .add(
+                Annotation::builder(15..16)
+                    .severity(Severity::Error)
+                    .header("first line\nsecond line")
+                    .code("E1")
+                    .priority(-1)
+                    .build()?,
+            )?
+            .warning(4..5, None, None)?;
+        let mut other = AnnotationList::new("a.rs", "fn main() {}\n");
+        other.help(3..7, None, "consider renaming")?;
+        let expected = "b.rs:1:5: warning\n\
+                        b.rs:2: note: no column\n\
+                        b.rs:2:5: error[E1]: first line\n\
+                        b.rs:2:5: info: same place\n";
+        assert_eq!(list.to_quickfix(), expected);
+        let report: MultiFileReport = vec![list, other].into_iter().collect();
+        assert_eq!(
+            report.to_quickfix(),
+            format!("a.rs:1:4: help: consider renaming\n{}", expected)
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "lsp")]
+    #[test]
+    fn test_to_lsp() -> Result<()> {
+        use crate::lsp::{
+            CodeDescription, DiagnosticRelatedInformation, DiagnosticSeverity, Location, Position,
+            Range,
+        };
+        let pos = |line, character| Position { line, character };
+        let source = "let x = 1;\nx + \"é\"\n";
+        let mut annotation = Annotation::builder(15..18)
+            .header("mismatched types")
+            .text("expected integer")
+            .code("E0308")
+            .url("https://example.com/E0308")
+            .build()?;
+        annotation
+            .add_secondary(0..9, "declared here")?
+            .add_help("use a number");
+        let mut list = AnnotationList::new("file:///main.rs", source);
+        list.add(annotation.clone())?.add_multiline(
+            Annotation::builder(4..13)
+                .severity(Severity::Help)
+                .build()?,
+        )?;
+        let diagnostic = annotation.to_lsp("file:///main.rs", source, &LineIndex::new(source));
+        // `é` is two bytes, but a single UTF-16 code unit
+        let primary = Range {
+            start: pos(1, 4),
+            end: pos(1, 6),
+        };
+        assert_eq!(diagnostic.range, primary);
+        assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::Error));
+        assert_eq!(diagnostic.code.as_deref(), Some("E0308"));
+        assert_eq!(
+            diagnostic.code_description,
+            Some(CodeDescription {
+                href: "https://example.com/E0308".into()
+            })
+        );
+        assert_eq!(diagnostic.message, "mismatched types\nexpected integer");
+        let location = |range| Location {
+            uri: "file:///main.rs".into(),
+            range,
+        };
+        assert_eq!(
+            diagnostic.related_information,
+            Some(vec![
+                DiagnosticRelatedInformation {
+                    location: location(Range {
+                        start: pos(0, 0),
+                        end: pos(0, 9),
+                    }),
+                    message: "declared here".into(),
+                },
+                DiagnosticRelatedInformation {
+                    location: location(primary),
+                    message: "help: use a number".into(),
+                },
+            ])
+        );
+        let diagnostics = list.to_lsp_diagnostics();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[0].range,
+            Range {
+                start: pos(0, 4),
+                end: pos(1, 2),
+            }
+        );
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::Hint));
+        assert_eq!(diagnostics[0].related_information, None);
+        assert_eq!(diagnostics[1], diagnostic);
+        Ok(())
+    }
+
+    #[test]
+    fn test_column_encoding() -> Result<()> {
+        let mut list = AnnotationList::new("main.rs", "x\n\"é𝄞\" + 1\n");
+        list.error(11..12, "no implementation for `&str + {integer}`", None)?;
+        let line = &list.lines[1];
+        assert_eq!(line.column(11, ColumnEncoding::Utf8), 10);
+        assert_eq!(line.column(11, ColumnEncoding::Utf16), 7);
+        assert_eq!(line.column(11, ColumnEncoding::Utf32), 6);
+        // Inside of `𝄞`, rounded up to the next char
+        assert_eq!(line.column(6, ColumnEncoding::Utf16), 5);
+        let options = RenderOptions {
+            column_encoding: ColumnEncoding::Utf16,
+            ..RenderOptions::default()
+        };
+        let buf = list.to_string_with_options(&options).unwrap();
+        assert!(buf.contains("--> main.rs:2:7\n"));
+        assert_eq!(
+            list.to_compact_string()
+                .unwrap()
+                .matches("main.rs:2:10")
+                .count(),
+            1
+        );
+        assert_eq!(
+            list.to_compact_string_with_options(&options)
+                .unwrap()
+                .matches("main.rs:2:7")
+                .count(),
+            1
+        );
+        assert!(list
+            .to_short_string_with_options(&options)
+            .unwrap()
+            .starts_with("main.rs:2:7: error"));
+        assert!(list.to_quickfix().starts_with("main.rs:2:10: error"));
+        assert!(list
+            .to_quickfix_with_options(&options)
+            .starts_with("main.rs:2:7: error"));
+        let located = |options: &RenderOptions| {
+            list.located_annotations_with_options(options)
+                .map(|(line, column, _)| (line, column))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(located(&RenderOptions::default()), vec![(2, 10)]);
+        assert_eq!(located(&options), vec![(2, 7)]);
+        #[cfg(feature = "lsp")]
+        {
+            let diagnostics = list.to_lsp_diagnostics();
+            assert_eq!(
+                diagnostics[0].range.start,
+                lsp::Position {
+                    line: 1,
+                    character: 6
+                }
+            );
+            assert_eq!(lsp::position_encoding_kind(ColumnEncoding::Utf16), "utf-16");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_owned() -> Result<()> {
+        fn build(filename: &str) -> Result<MultiFileReport<'static>> {
+            let source = format!("fn {}() {{}}\n", filename.trim_end_matches(".rs"));
+            let mut list = AnnotationList::new(filename, &source);
+            list.warning(3..7, "function is never used", None)?;
+            let report: MultiFileReport = vec![list].into_iter().collect();
+            Ok(report.into_owned())
+        }
+        let report = build("main.rs")?;
+        let expected = r#"warning: function is never used
+  --> main.rs:1:4
+   |
+ 1 | fn main() {}
+   |    ^^^^
+"#;
+        assert_eq!(report.to_string().unwrap(), expected);
+
+        let source = String::from("fn main() {}\n");
+        let mut borrowed = AnnotationList::new("main.rs", &source);
+        borrowed.warning(3..7, "function is never used", None)?;
+        let mut owned = AnnotationList::from_string("main.rs", source.clone());
+        owned.warning(3..7, "function is never used", None)?;
+        assert_eq!(owned, borrowed);
+        assert_eq!(borrowed.into_owned(), owned);
+        Ok(())
+    }
+
+    #[test]
+    fn test_line_boundary_check() -> Result<()> {
+        // Range is shorter than the line, but still crosses its end
+        let mut list = AnnotationList::new("fname", "ab\ncd");
+        let annotation = Annotation::error(1..4, None, None)?;
+        assert_eq!(
+            list.lines[0].add(annotation.clone()).unwrap_err(),
+            Error::MultilineRange(1, 4)
+        );
+        assert_eq!(
+            list.add(annotation).unwrap_err().error,
+            Error::MultilineRange(1, 4)
+        );
+        list.lines[0].add(Annotation::error(1..3, None, None)?)?;
+        assert_eq!(list.annotations().count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_non_ascii() -> Result<()> {
+        // Newline offsets are byte offsets, not char indices
+        let source = "héllo\nwörld\n";
+        let list = AnnotationList::new("fname", source);
+        let mut lines = list.annotated_lines().iter();
+        assert_start_content(lines.next().unwrap(), 0, "héllo\n");
+        assert_start_content(lines.next().unwrap(), 7, "wörld\n");
+        assert!(lines.next().is_none());
+        let mut list = AnnotationList::new("fname", source);
+        list.error(7..8, None, "here")?;
+        assert!(list
+            .to_string()
+            .unwrap()
+            .contains(" 2 | wörld\n   | ^ here\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_align_labels() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "let value = 1;\nx");
+        list.error(0..3, None, "short\nsecond line")?
+            .error(4..9, None, "long")?
+            .error(15..16, None, "other line")?;
+        let options = RenderOptions {
+            align_labels: true,
+            ..RenderOptions::default()
+        };
+        let buf = list.to_string_with_options(&options).unwrap();
+        assert!(buf.contains("\n   | ^^^       short\n   |           second line\n"));
+        assert!(buf.contains("\n   |     ^^^^^ long\n"));
+        // Lines are aligned independently
+        assert!(buf.contains("\n   | ^ other line\n"));
+        assert_ne!(buf, list.to_string().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_label_back_reference_column() -> Result<()> {
+        let mut list = AnnotationList::new("fname", "é = 1;\né = 2;");
+        list.define_label("def", "defined here");
+        for range in [3..4, 11..12].iter().cloned() {
+            list.add(Annotation::builder(range).label_id("def").build()?)?;
+        }
+        assert!(list
+            .to_string()
+            .unwrap()
+            .contains("^ defined here (see 1:4)\n"));
+        let options = RenderOptions {
+            column_encoding: ColumnEncoding::Utf32,
+            ..RenderOptions::default()
+        };
+        let buf = list.to_string_with_options(&options).unwrap();
+        assert!(buf.contains("^ defined here (see 1:3)\n"));
+        assert_eq!(buf.matches("(see").count(), 1);
+        Ok(())
+    }
 }
@@ -0,0 +1,110 @@
+use std::iter;
+
+/// Positions of line starts in a string. Building it requires scanning the whole string for
+/// newlines, so if you annotate the same source several times, build it once and pass it to
+/// [`AnnotationList::with_index`](crate::AnnotationList::with_index).
+/// ```rust
+/// # use show_my_errors::LineIndex;
+/// let index = LineIndex::new("first\nsecond\n");
+/// assert_eq!(index.line_count(), 2);
+/// assert_eq!(index.locate(8), Some((2, 3)));
+/// assert_eq!(index.offset_at(2, 3), Some(8));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineIndex {
+    starts: Vec<usize>,
+    len: usize,
+}
+
+impl LineIndex {
+    /// Scan `string` for newlines. Newline at the end of the string doesn't start a new line,
+    /// like in most editors. Empty string consists of a single empty line.
+    pub fn new(string: &str) -> Self {
+        Self::scan(string, false)
+    }
+
+    /// Like [`LineIndex::new`], but newline at the end of the string starts a new empty line,
+    /// which can be annotated to point at the end of file.
+    /// ```rust
+    /// # use show_my_errors::{AnnotationList, LineIndex};
+    /// # fn main() -> Result<(), show_my_errors::Error> {
+    /// let source = "first\nsecond\n";
+    /// assert_eq!(LineIndex::new(source).line_count(), 2);
+    /// let index = LineIndex::with_trailing_line(source);
+    /// assert_eq!(index.line_count(), 3);
+    /// let mut list = AnnotationList::with_index("file", source, &index);
+    /// list.error(13..13, "unexpected end of file", None)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_trailing_line(string: &str) -> Self {
+        Self::scan(string, true)
+    }
+
+    fn scan(string: &str, trailing_line: bool) -> Self {
+        let len = string.len();
+        let starts = iter::once(0)
+            .chain(
+                string
+                    .char_indices()
+                    .filter(|(_idx, c)| *c == '\n')
+                    .map(|(idx, _c)| idx + 1),
+            )
+            .filter(|&start| start < len || start == 0 || trailing_line)
+            .collect();
+        Self { starts, len }
+    }
+
+    /// Number of lines in the string
+    pub fn line_count(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Length of the indexed string in bytes
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the indexed string is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Byte ranges of all lines, including trailing newlines
+    pub(crate) fn line_bounds(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.starts.iter().enumerate().map(move |(idx, &start)| {
+            (start, self.starts.get(idx + 1).copied().unwrap_or(self.len))
+        })
+    }
+
+    /// 0-based index and start offset of the line containing `offset`. Offsets past the end
+    /// belong to the last line.
+    pub(crate) fn line_start(&self, offset: usize) -> (usize, usize) {
+        let idx = self.starts.partition_point(|&start| start <= offset) - 1;
+        (idx, self.starts[idx])
+    }
+
+    /// Find 1-based line number and 1-based column (in bytes) of the offset, as shown in the
+    /// location line. Returns `None` if the offset is outside of the string. The end of the
+    /// string is only located if the last line is empty.
+    pub fn locate(&self, offset: usize) -> Option<(usize, usize)> {
+        if offset > self.len || (offset == self.len && self.starts.last() != Some(&self.len)) {
+            return None;
+        }
+        let (idx, start) = self.line_start(offset);
+        Some((idx + 1, offset - start + 1))
+    }
+
+    /// Find the offset of the 1-based line number and 1-based column (in bytes). This is the
+    /// inverse of [`.locate()`](LineIndex::locate). Returns `None` if there is no such
+    /// position, including columns past the end of the line.
+    pub fn offset_at(&self, line: usize, column: usize) -> Option<usize> {
+        let (start, end) = self.line_bounds().nth(line.checked_sub(1)?)?;
+        let offset = start + column.checked_sub(1)?;
+        if offset < end || (offset == start && start == end) {
+            Some(offset)
+        } else {
+            None
+        }
+    }
+}
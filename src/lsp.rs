@@ -0,0 +1,282 @@
+//! Conversion of annotations to [Language Server Protocol][lsp] diagnostics. Types here
+//! mirror the ones from [lsp-types](https://docs.rs/lsp-types) field by field, so they can
+//! be converted without depending on a particular version of that crate:
+//! ```rust
+//! # mod lsp_types {
+//! #     #[derive(Default)]
+//! #     pub struct Position { pub line: u32, pub character: u32 }
+//! #     #[derive(Default)]
+//! #     pub struct Range { pub start: Position, pub end: Position }
+//! #     #[derive(Debug, PartialEq)]
+//! #     pub struct DiagnosticSeverity(i32);
+//! #     impl DiagnosticSeverity {
+//! #         pub const ERROR: Self = Self(1);
+//! #         pub const WARNING: Self = Self(2);
+//! #         pub const INFORMATION: Self = Self(3);
+//! #         pub const HINT: Self = Self(4);
+//! #     }
+//! #     pub enum NumberOrString { Number(i32), String(String) }
+//! #     #[derive(Default)]
+//! #     pub struct Diagnostic {
+//! #         pub range: Range,
+//! #         pub severity: Option<DiagnosticSeverity>,
+//! #         pub code: Option<NumberOrString>,
+//! #         pub message: String,
+//! #     }
+//! # }
+//! use show_my_errors::{lsp, Annotation, LineIndex};
+//!
+//! fn convert_position(position: lsp::Position) -> lsp_types::Position {
+//!     lsp_types::Position { line: position.line, character: position.character }
+//! }
+//!
+//! fn convert(diagnostic: lsp::Diagnostic) -> lsp_types::Diagnostic {
+//!     lsp_types::Diagnostic {
+//!         range: lsp_types::Range {
+//!             start: convert_position(diagnostic.range.start),
+//!             end: convert_position(diagnostic.range.end),
+//!         },
+//!         severity: diagnostic.severity.map(|severity| match severity {
+//!             lsp::DiagnosticSeverity::Error => lsp_types::DiagnosticSeverity::ERROR,
+//!             lsp::DiagnosticSeverity::Warning => lsp_types::DiagnosticSeverity::WARNING,
+//!             lsp::DiagnosticSeverity::Information => lsp_types::DiagnosticSeverity::INFORMATION,
+//!             lsp::DiagnosticSeverity::Hint => lsp_types::DiagnosticSeverity::HINT,
+//!         }),
+//!         code: diagnostic.code.map(lsp_types::NumberOrString::String),
+//!         message: diagnostic.message,
+//!         ..Default::default()
+//!     }
+//! }
+//!
+//! # fn main() -> Result<(), show_my_errors::Error> {
+//! let source = "let café = 1;\n";
+//! let annotation = Annotation::warning(10..11, "unexpected `=`", None)?;
+//! let diagnostic = convert(annotation.to_lsp("file:///main.rs", source, &LineIndex::new(source)));
+//! assert_eq!(diagnostic.range.start.character, 9);
+//! assert_eq!(diagnostic.severity, Some(lsp_types::DiagnosticSeverity::WARNING));
+//! # Ok(())
+//! # }
+//! ```
+//! Lines and characters are 0-based. Characters are counted in UTF-16 code units by default,
+//! the encoding mandated by the protocol. Use [`Annotation::to_lsp_with_encoding`] and
+//! [`AnnotationList::to_lsp_diagnostics_with_encoding`] for clients that negotiated another
+//! one, see also [`position_encoding_kind`].
+//!
+//! [lsp]: https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/
+use crate::{Annotation, AnnotationList, ColumnEncoding, LineIndex, NoteKind};
+
+/// Name of the encoding in the `positionEncoding` server capability, e.g. `"utf-16"`
+pub fn position_encoding_kind(encoding: ColumnEncoding) -> &'static str {
+    match encoding {
+        ColumnEncoding::Utf8 => "utf-8",
+        ColumnEncoding::Utf16 => "utf-16",
+        ColumnEncoding::Utf32 => "utf-32",
+    }
+}
+
+/// Position in a text document, `line` and `character` are 0-based
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Position {
+    pub line: u32,
+    pub character: u32,
+}
+
+/// Range in a text document, `end` is exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// Range inside of a document with the given URI
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Location {
+    pub uri: String,
+    pub range: Range,
+}
+
+/// Severity of a diagnostic. Values are the same as in the protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DiagnosticSeverity {
+    Error = 1,
+    Warning = 2,
+    Information = 3,
+    Hint = 4,
+}
+
+impl From<crate::Severity> for DiagnosticSeverity {
+    /// Errors and warnings keep their severity, [`Severity::Help`](crate::Severity::Help)
+    /// becomes a hint, and everything else is information. Custom severities are mapped like
+    /// the closest built-in one.
+    fn from(severity: crate::Severity) -> Self {
+        use crate::Severity;
+        match severity.builtin() {
+            Severity::Error => Self::Error,
+            Severity::Warning => Self::Warning,
+            Severity::Help => Self::Hint,
+            _ => Self::Information,
+        }
+    }
+}
+
+/// Link to documentation of a diagnostic code
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CodeDescription {
+    pub href: String,
+}
+
+/// Message related to a diagnostic, e.g. a secondary span or a note
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DiagnosticRelatedInformation {
+    pub location: Location,
+    pub message: String,
+}
+
+/// Diagnostic, such as a compiler error or warning
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Diagnostic {
+    pub range: Range,
+    pub severity: Option<DiagnosticSeverity>,
+    pub code: Option<String>,
+    pub code_description: Option<CodeDescription>,
+    pub source: Option<String>,
+    pub message: String,
+    pub related_information: Option<Vec<DiagnosticRelatedInformation>>,
+}
+
+/// Build a diagnostic, `position` converts byte offsets to positions
+fn diagnostic(
+    annotation: &Annotation,
+    uri: &str,
+    position: impl Fn(usize) -> Position,
+) -> Diagnostic {
+    let range = |range: &std::ops::Range<usize>| Range {
+        start: position(range.start),
+        end: position(range.end),
+    };
+    let location = |range| Location {
+        uri: uri.into(),
+        range,
+    };
+    let primary = range(annotation.range());
+    let message = match (&annotation.header, &annotation.text) {
+        (Some(header), Some(text)) => format!("{}\n{}", header, text),
+        (Some(message), None) | (None, Some(message)) => message.clone(),
+        (None, None) => String::new(),
+    };
+    let related: Vec<_> = annotation
+        .secondary_spans()
+        .iter()
+        .map(|span| DiagnosticRelatedInformation {
+            location: location(range(span.range())),
+            message: span.text.clone().unwrap_or_default(),
+        })
+        .chain(annotation.notes().iter().map(|note| {
+            let kind = match note.kind {
+                NoteKind::Note => "note",
+                NoteKind::Help => "help",
+            };
+            DiagnosticRelatedInformation {
+                location: location(primary),
+                message: format!("{}: {}", kind, note.text),
+            }
+        }))
+        .collect();
+    Diagnostic {
+        range: primary,
+        severity: Some(annotation.severity.into()),
+        code: annotation.code.clone(),
+        code_description: annotation.url.clone().map(|href| CodeDescription { href }),
+        source: None,
+        message,
+        related_information: if related.is_empty() {
+            None
+        } else {
+            Some(related)
+        },
+    }
+}
+
+impl Annotation {
+    /// Convert annotation to an LSP diagnostic for the document with given URI and `source`,
+    /// using `index` built from `source` to find positions. Characters are counted in UTF-16
+    /// code units. Message consists of header and text, each on its own line. Secondary spans
+    /// and notes become related information, notes are attached to the annotated fragment
+    /// and prefixed with `note: ` or `help: `.
+    /// ```rust
+    /// # use show_my_errors::{Annotation, LineIndex, lsp};
+    /// # fn main() -> Result<(), show_my_errors::Error> {
+    /// let source = "first\nsecond\n";
+    /// let diagnostic = Annotation::warning(8..11, "unused", None)?
+    ///     .to_lsp("file:///tmp/file.txt", source, &LineIndex::new(source));
+    /// assert_eq!(diagnostic.range.start, lsp::Position { line: 1, character: 2 });
+    /// assert_eq!(diagnostic.range.end, lsp::Position { line: 1, character: 5 });
+    /// assert_eq!(diagnostic.severity, Some(lsp::DiagnosticSeverity::Warning));
+    /// assert_eq!(diagnostic.message, "unused");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_lsp(&self, uri: &str, source: &str, index: &LineIndex) -> Diagnostic {
+        self.to_lsp_with_encoding(uri, source, index, ColumnEncoding::Utf16)
+    }
+
+    /// Like [`.to_lsp()`](Annotation::to_lsp), but counts characters in given units
+    /// ```rust
+    /// # use show_my_errors::{Annotation, ColumnEncoding, LineIndex, lsp};
+    /// # fn main() -> Result<(), show_my_errors::Error> {
+    /// let source = "😀 = 1";
+    /// let diagnostic = Annotation::error(5..6, "unexpected `=`", None)?.to_lsp_with_encoding(
+    ///     "file:///tmp/smile.txt",
+    ///     source,
+    ///     &LineIndex::new(source),
+    ///     ColumnEncoding::Utf8,
+    /// );
+    /// assert_eq!(diagnostic.range.start, lsp::Position { line: 0, character: 5 });
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_lsp_with_encoding(
+        &self,
+        uri: &str,
+        source: &str,
+        index: &LineIndex,
+        encoding: ColumnEncoding,
+    ) -> Diagnostic {
+        diagnostic(self, uri, |offset| {
+            let (idx, start) = index.line_start(offset);
+            Position {
+                line: idx as u32,
+                character: encoding.column(&source[start..], offset - start) as u32,
+            }
+        })
+    }
+}
+
+impl AnnotationList<'_> {
+    /// Convert annotations to LSP diagnostics in display order, see [`Annotation::to_lsp`].
+    /// Filename is used as the document URI as is. Characters are counted in UTF-16 code
+    /// units.
+    pub fn to_lsp_diagnostics(&self) -> Vec<Diagnostic> {
+        self.to_lsp_diagnostics_with_encoding(ColumnEncoding::Utf16)
+    }
+
+    /// Like [`.to_lsp_diagnostics()`](AnnotationList::to_lsp_diagnostics), but counts
+    /// characters in given units
+    pub fn to_lsp_diagnostics_with_encoding(&self, encoding: ColumnEncoding) -> Vec<Diagnostic> {
+        let position = |offset: usize| {
+            let idx = self
+                .lines
+                .partition_point(|line| line.start <= offset)
+                .saturating_sub(1);
+            Position {
+                line: idx as u32,
+                character: self.lines.get(idx).map_or(0, |line| {
+                    encoding.column(&line.content, offset.saturating_sub(line.start))
+                }) as u32,
+            }
+        };
+        self.blocks()
+            .map(|(_idx, annotation)| diagnostic(annotation, &self.filename, position))
+            .collect()
+    }
+}
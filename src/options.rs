@@ -0,0 +1,186 @@
+/// Layout settings of the output, separate from colors in [`Stylesheet`](crate::Stylesheet).
+/// [`.show()`](crate::AnnotationList::show) and other methods not taking options use
+/// [`RenderOptions::default()`].
+/// ```rust
+/// # use show_my_errors::{AnnotationList, RenderOptions, Stylesheet};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut list = AnnotationList::new("main.c", "\treturn 0");
+/// list.error(8..9, "expected `;`", None)?;
+/// let options = RenderOptions {
+///     tab_width: Some(4),
+///     ..RenderOptions::default()
+/// };
+/// let mut buf = termcolor::Buffer::no_color();
+/// list.show_with_options(&mut buf, &Stylesheet::monochrome(), &options)?;
+/// let output = String::from_utf8(buf.into_inner())?;
+/// assert!(output.contains(" 1 |     return 0\n   |            ^\n"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    /// Show tabs in line content as this many spaces, and place carets accordingly. Defaults
+    /// to `None`, which leaves tabs as is and uses [`char_width`](RenderOptions::char_width)
+    /// for them.
+    pub tab_width: Option<usize>,
+    /// Number of blank lines between annotations. Defaults to 1.
+    pub separator_lines: usize,
+    /// Mark annotations covering the whole line content with a single caret and an
+    /// ellipsis (`^…`, or `^...` with [`ascii_only`](crate::Stylesheet::ascii_only)) instead
+    /// of a caret under every char. Disabled by default.
+    pub succinct_full_line: bool,
+    /// Number of unannotated source lines shown before and after the annotated line(s).
+    /// Defaults to 0.
+    pub context_lines: usize,
+    /// Finish the output with a rustc-style summary footer, e.g.
+    /// `error: aborting due to 2 previous errors; 1 warning emitted`. Nothing is added if
+    /// there are neither errors nor warnings. Wording is taken from [`Labels`](crate::Labels),
+    /// see also [`SeverityCounts::summary`](crate::SeverityCounts::summary). Disabled by
+    /// default.
+    pub summary: bool,
+    /// Units in which columns are counted in the location line (`file:line:col`). Defaults
+    /// to [`ColumnEncoding::Utf8`]. [`Annotation::column`](crate::Annotation::column) is
+    /// shown as is regardless of this setting.
+    pub column_encoding: ColumnEncoding,
+    /// Start texts of all annotations on the same line at the same column, one space after
+    /// the rightmost caret among them, so messages of annotations stacked under one line
+    /// read as a column. Disabled by default;
+    /// [`.show_grouped()`](crate::AnnotationList::show_grouped) always aligns them.
+    /// ```rust
+    /// # use show_my_errors::{AnnotationList, RenderOptions, Stylesheet};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("main.rs", "let value = 1;");
+    /// list.warning(0..3, None, "short")?.error(4..9, None, "long")?;
+    /// let options = RenderOptions { align_labels: true, ..RenderOptions::default() };
+    /// let mut buf = termcolor::Buffer::no_color();
+    /// list.show_with_options(&mut buf, &Stylesheet::monochrome(), &options)?;
+    /// let output = String::from_utf8(buf.into_inner())?;
+    /// assert!(output.contains("   | ^^^       short\n"));
+    /// assert!(output.contains("   |     ^^^^^ long\n"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub align_labels: bool,
+    /// Show the `--> file:line:col` location line. Defaults to `true`; you may want to
+    /// disable it when there is no meaningful filename, e.g. in a REPL.
+    pub show_location: bool,
+    /// Show the annotated line content. Defaults to `true`. If disabled, only carets are
+    /// shown under the gutter, at the columns they'd have under the content. This is useful
+    /// for very long lines, e.g. in tabular data, together with the location line.
+    pub show_content: bool,
+    /// Show column numbers above the line content, useful for debugging offsets. Columns
+    /// are counted in display width, see [`char_width`](RenderOptions::char_width).
+    pub show_ruler: bool,
+    /// Underline annotated fragment of the line content using terminal underline attribute
+    /// instead of drawing carets below it. Annotation text is shown below the fragment, and
+    /// if there is none, the row is omitted altogether. Without colors the fragment isn't
+    /// marked at all, so this is only useful with colored output.
+    pub underline_span: bool,
+    /// Separator between severity name and annotation header. Defaults to `": "`.
+    pub header_separator: String,
+    /// Show byte range of annotation (e.g. `[4..7]`) after its text. Useful for debugging
+    /// offsets when integrating this library.
+    pub show_range: bool,
+    /// Color line number of annotated line by the most severe annotation on it, instead of
+    /// using [`linenr`](crate::Stylesheet::linenr) color
+    pub linenr_by_severity: bool,
+    /// Show `\` in filename as `/`, so paths look the same on all platforms
+    pub normalize_separators: bool,
+    /// Replace `{span}` in annotation header and text with the annotated fragment of source,
+    /// e.g. to render ``"`{span}` is deprecated"`` as ``"`foo` is deprecated"``. Disabled by
+    /// default, so literal braces in messages are left intact.
+    pub expand_span: bool,
+    /// Uncolored prefix added to every line of the output, useful to embed messages into
+    /// indented output. Blank lines between annotations are left empty.
+    pub indent: String,
+    /// Display width of a char, used to place carets under annotated fragment. Defaults to 1
+    /// for every char. You may want to use [unicode-width](https://docs.rs/unicode-width)
+    /// here, or a custom function if your frontend uses a font with unusual widths.
+    /// ```rust
+    /// # use show_my_errors::RenderOptions;
+    /// let options = RenderOptions {
+    ///     char_width: |c| if c == '\t' { 4 } else { 1 },
+    ///     ..RenderOptions::default()
+    /// };
+    /// ```
+    pub char_width: fn(char) -> usize,
+}
+
+/// Units in which columns are counted. Columns of fragments not on char boundary are
+/// rounded up to the next char, except for [`ColumnEncoding::Utf8`].
+/// ```rust
+/// # use show_my_errors::{AnnotationList, ColumnEncoding, RenderOptions, Stylesheet};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut list = AnnotationList::new("smile.txt", "😀 = 1");
+/// list.error(5..6, "unexpected `=`", None)?;
+/// for (encoding, column) in [
+///     (ColumnEncoding::Utf8, 6),
+///     (ColumnEncoding::Utf16, 4),
+///     (ColumnEncoding::Utf32, 3),
+/// ].iter().copied() {
+///     let options = RenderOptions { column_encoding: encoding, ..RenderOptions::default() };
+///     let mut buf = termcolor::Buffer::no_color();
+///     list.show_with_options(&mut buf, &Stylesheet::monochrome(), &options)?;
+///     let output = String::from_utf8(buf.into_inner())?;
+///     assert!(output.contains(&format!("--> smile.txt:1:{}\n", column)));
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ColumnEncoding {
+    /// Bytes of UTF-8, like rustc does
+    #[default]
+    Utf8,
+    /// UTF-16 code units, like LSP and JavaScript-based editors do by default
+    Utf16,
+    /// Unicode scalar values, i.e. Rust [`char`]s
+    Utf32,
+}
+
+impl ColumnEncoding {
+    /// 0-based column of `offset` in `line`
+    pub(crate) fn column(self, line: &str, offset: usize) -> usize {
+        let chars = line.char_indices().take_while(|(idx, _c)| *idx < offset);
+        match self {
+            Self::Utf8 => offset,
+            Self::Utf16 => chars.map(|(_idx, c)| c.len_utf16()).sum(),
+            Self::Utf32 => chars.count(),
+        }
+    }
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            tab_width: None,
+            separator_lines: 1,
+            succinct_full_line: false,
+            context_lines: 0,
+            summary: false,
+            column_encoding: ColumnEncoding::default(),
+            align_labels: false,
+            show_location: true,
+            show_content: true,
+            show_ruler: false,
+            underline_span: false,
+            header_separator: ": ".into(),
+            show_range: false,
+            linenr_by_severity: false,
+            normalize_separators: false,
+            expand_span: false,
+            indent: String::new(),
+            char_width: |_c| 1,
+        }
+    }
+}
+
+impl RenderOptions {
+    /// Display width of a char in line content
+    pub(crate) fn display_width(&self, c: char) -> usize {
+        match (c, self.tab_width) {
+            ('\t', Some(tab_width)) => tab_width,
+            (c, _) => (self.char_width)(c),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use std::io;
+use termcolor::{ColorSpec, WriteColor};
+
+/// Something rendered annotations can be written to. This allows writing text directly
+/// into a [`String`] without going through `Vec<u8>`.
+pub(crate) trait Output {
+    fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()>;
+    fn reset(&mut self) -> io::Result<()>;
+    fn write_str(&mut self, s: &str) -> io::Result<()>;
+    /// Whether escape sequences may be written, e.g. for hyperlinks
+    fn supports_color(&self) -> bool;
+}
+
+/// [`Output`] wrapper for streams implementing [`WriteColor`]
+pub(crate) struct Stream<W>(pub W);
+
+impl<W: io::Write + WriteColor> Output for Stream<W> {
+    fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()> {
+        self.0.set_color(spec)
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        self.0.reset()
+    }
+
+    fn write_str(&mut self, s: &str) -> io::Result<()> {
+        self.0.write_all(s.as_bytes())
+    }
+
+    fn supports_color(&self) -> bool {
+        self.0.supports_color()
+    }
+}
+
+/// Monochrome output, colors are ignored
+impl Output for String {
+    fn set_color(&mut self, _spec: &ColorSpec) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write_str(&mut self, s: &str) -> io::Result<()> {
+        self.push_str(s);
+        Ok(())
+    }
+
+    fn supports_color(&self) -> bool {
+        false
+    }
+}
+
+/// [`io::Write`] adapter appending to a [`String`]. Used with [`termcolor::Ansi`] to write
+/// colored output without intermediate `Vec<u8>`.
+pub(crate) struct StringWriter<'a>(pub &'a mut String);
+
+impl io::Write for StringWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let s = std::str::from_utf8(buf)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        self.0.push_str(s);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use crate::{render::write_events, AnnotationList, RenderEvent, RenderOptions, Stylesheet};
+use std::{io, iter, thread};
+
+impl AnnotationList<'_> {
+    /// Like [`.to_bytes()`](AnnotationList::to_bytes), but annotation blocks are rendered
+    /// concurrently on all available cores. Output is byte-identical to
+    /// [`.to_bytes()`](AnnotationList::to_bytes). This only pays off for lists with
+    /// thousands of annotations.
+    pub fn to_bytes_parallel(&self) -> io::Result<Vec<u8>> {
+        let stylesheet = Stylesheet::monochrome();
+        let options = RenderOptions::default();
+        let blocks: Vec<_> = self.blocks().collect();
+        let linenr_width = self.linenr_width(&options, &blocks);
+        let label_refs = self.label_refs(&options);
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk_size = ((blocks.len() + threads - 1) / threads).max(1);
+        let chunks: Vec<String> = thread::scope(|scope| {
+            let handles: Vec<_> = blocks
+                .chunks(chunk_size)
+                .enumerate()
+                .map(|(chunk_idx, chunk)| {
+                    let stylesheet = &stylesheet;
+                    let options = &options;
+                    let label_refs = &label_refs;
+                    scope.spawn(move || {
+                        let mut events = vec![];
+                        for (block_idx, &(idx, annotation)) in chunk.iter().enumerate() {
+                            if chunk_idx != 0 || block_idx != 0 {
+                                events.extend(
+                                    iter::repeat(RenderEvent::Blank).take(options.separator_lines),
+                                );
+                            }
+                            self.annotation_events(
+                                &mut events,
+                                stylesheet,
+                                options,
+                                idx,
+                                annotation,
+                                linenr_width,
+                                None,
+                                label_refs,
+                            );
+                        }
+                        let mut buf = String::new();
+                        write_events(&mut buf, stylesheet, options, &events)
+                            .expect("writing to String can't fail");
+                        buf
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("rendering thread panicked"))
+                .collect()
+        });
+        Ok(chunks.concat().into_bytes())
+    }
+}
@@ -0,0 +1,12 @@
+//! Commonly used types. [`Error`](crate::Error) and [`Result`](crate::Result) are not
+//! included, since they would shadow the standard ones.
+//! ```rust
+//! use show_my_errors::prelude::*;
+//! # fn main() -> Result<(), show_my_errors::Error> {
+//! let mut list = AnnotationList::new("hello.txt", "Hello world!");
+//! list.add(Annotation::new(0..5, Severity::Info, "header", None)?)?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub use crate::{Annotation, AnnotationList, AnnotationText, Severity, Stylesheet};
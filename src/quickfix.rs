@@ -0,0 +1,77 @@
+use crate::{render::write_events, AnnotationList, MultiFileReport, RenderOptions, Stylesheet};
+use std::cmp;
+
+impl AnnotationList<'_> {
+    /// Format annotations as `file:line:col: severity: message` lines that can be loaded
+    /// directly into Vim's quickfix list (`:cexpr`, `:cfile`) or Emacs compilation mode.
+    /// Lines are the same as in [`.to_short_string()`](AnnotationList::to_short_string), but
+    /// output doesn't depend on priorities or insertion order: annotations are sorted by line,
+    /// column, severity (most severe first) and message. Columns are 1-based and counted in
+    /// bytes, as Vim expects, unless overridden by
+    /// [`Annotation::column`](crate::Annotation::column). Line notes have no column.
+    /// ```rust
+    /// # use show_my_errors::AnnotationList;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("hello.txt", "Hello world!");
+    /// list.error(11..12, None, "unexpected `!`")?
+    ///     .warning(4..7, "punctuation problem", "you probably forgot a comma")?;
+    /// assert_eq!(
+    ///     list.to_quickfix(),
+    ///     "hello.txt:1:5: warning: punctuation problem\n\
+    ///      hello.txt:1:12: error: unexpected `!`\n",
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_quickfix(&self) -> String {
+        self.to_quickfix_with_options(&RenderOptions::default())
+    }
+
+    /// Like [`.to_quickfix()`](AnnotationList::to_quickfix), but uses given layout settings,
+    /// e.g. [`RenderOptions::column_encoding`] for editors counting columns in other units
+    pub fn to_quickfix_with_options(&self, options: &RenderOptions) -> String {
+        let mut output = String::new();
+        self.quickfix_into(&mut output, options);
+        output
+    }
+
+    fn quickfix_into(&self, output: &mut String, options: &RenderOptions) {
+        let stylesheet = Stylesheet::monochrome();
+        let mut blocks: Vec<_> = self.blocks().collect();
+        blocks.sort_by_key(|&(idx, annotation)| {
+            let column = if annotation.is_line_note() {
+                None
+            } else {
+                Some(self.lines[idx].display_column(annotation, options.column_encoding))
+            };
+            let message = annotation
+                .message()
+                .and_then(|message| message.lines().next());
+            (idx, column, cmp::Reverse(annotation.severity), message)
+        });
+        let events: Vec<_> = blocks
+            .into_iter()
+            .map(|(idx, annotation)| {
+                self.short_event(&stylesheet, options, &self.filename, idx, annotation)
+            })
+            .collect();
+        write_events(output, &stylesheet, options, &events).expect("writing to String can't fail");
+    }
+}
+
+impl MultiFileReport<'_> {
+    /// Format annotations of all files as quickfix lines, see
+    /// [`AnnotationList::to_quickfix`]. Files are ordered by their names.
+    pub fn to_quickfix(&self) -> String {
+        self.to_quickfix_with_options(&RenderOptions::default())
+    }
+
+    /// Like [`.to_quickfix()`](MultiFileReport::to_quickfix), but uses given layout settings
+    pub fn to_quickfix_with_options(&self, options: &RenderOptions) -> String {
+        let mut output = String::new();
+        for list in self.sorted_lists() {
+            list.quickfix_into(&mut output, options);
+        }
+        output
+    }
+}
@@ -0,0 +1,1166 @@
+use crate::{
+    output::Output, AnnotatedLine, Annotation, AnnotationList, NoteKind, RenderOptions,
+    SecondarySpan, Severity, SeverityCounts, Stylesheet,
+};
+use std::{
+    cmp,
+    collections::HashMap,
+    io, iter,
+    ops::{Range, RangeInclusive},
+};
+use termcolor::ColorSpec;
+
+/// Role of a piece of rendered text. Role determines which [`Stylesheet`] color is used
+/// for the text, see [`Stylesheet::by_role`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum StyleRole {
+    /// Severity label, header, carets and annotation text
+    Severity(Severity),
+    /// Annotated fragment of the line content if [`RenderOptions::underline_span`] is set. Uses
+    /// severity color with underline attribute added.
+    Underline(Severity),
+    /// Carets under annotated fragment if [`Stylesheet::underline_carets`] is set and
+    /// [`Stylesheet::bold_caret_edges`] isn't. Uses severity color with underline attribute
+    /// added.
+    Caret(Severity),
+    /// First and last caret under annotated fragment if [`Stylesheet::bold_caret_edges`] is
+    /// set. Uses severity color with bold attribute added.
+    CaretEdge(Severity),
+    /// Carets between the edges if [`Stylesheet::bold_caret_edges`] is set. Uses severity
+    /// color with bold attribute removed.
+    CaretMiddle(Severity),
+    /// Diagnostic code in the header, see [`Annotation::code`]. Uses [`Stylesheet::code`]
+    /// color if it's set, severity color otherwise.
+    Code(Severity),
+    /// Documentation link of an annotation, see [`Annotation::url`]. Uses
+    /// [`content`](Stylesheet::content) color, and is made clickable if
+    /// [`Stylesheet::hyperlinks`] is set.
+    Link,
+    /// Line numbers column and `-->` arrow
+    LineNumber,
+    /// Filename and position in the location line
+    Filename,
+    /// Directory part of filename, if it's colored separately
+    Directory,
+    /// Content of annotated line
+    Content,
+}
+
+/// Piece of rendered text with a single style
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    /// Text of the segment, without newlines
+    pub text: String,
+    /// Role of the text, determining its style
+    pub role: StyleRole,
+}
+
+/// A single line of rendered output. Lines don't include trailing newline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RenderEvent {
+    /// Severity label and annotation header, e.g. `error: something went wrong`
+    SeverityHeader(Vec<Segment>),
+    /// Filename and position, e.g. `  --> file.txt:1:5`
+    LocationLine(Vec<Segment>),
+    /// Line numbers column without a number
+    Gutter(Vec<Segment>),
+    /// Column numbers above the line content, see [`RenderOptions::show_ruler`]
+    Ruler(Vec<Segment>),
+    /// Line number and annotated line content
+    SourceLine(Vec<Segment>),
+    /// Carets under the annotated fragment and first line of annotation text
+    CaretLine(Vec<Segment>),
+    /// Continuation line of multi-line annotation text
+    TextLine(Vec<Segment>),
+    /// Blank line separating annotations
+    Blank,
+}
+
+impl RenderEvent {
+    /// Get styled pieces of this line. [`RenderEvent::Blank`] has none.
+    pub fn segments(&self) -> &[Segment] {
+        match self {
+            Self::SeverityHeader(segments)
+            | Self::LocationLine(segments)
+            | Self::Gutter(segments)
+            | Self::Ruler(segments)
+            | Self::SourceLine(segments)
+            | Self::CaretLine(segments)
+            | Self::TextLine(segments) => segments,
+            Self::Blank => &[],
+        }
+    }
+}
+
+/// First use of each shared label: 1-based line and column of the annotation using it, and
+/// the annotation itself, see [`AnnotationList::define_label`]
+pub(crate) type LabelRefs<'a> = HashMap<&'a str, (usize, usize, &'a Annotation)>;
+
+/// Helper to build lists of segments, merging adjacent segments with the same role
+#[derive(Default)]
+struct Segments(Vec<Segment>);
+
+impl Segments {
+    fn push(&mut self, role: StyleRole, text: impl AsRef<str>) -> &mut Self {
+        let text = text.as_ref();
+        if text.is_empty() {
+            return self;
+        }
+        match self.0.last_mut() {
+            Some(last) if last.role == role => last.text.push_str(text),
+            _ => self.0.push(Segment {
+                text: text.into(),
+                role,
+            }),
+        }
+        self
+    }
+
+    fn push_n(&mut self, role: StyleRole, text: &str, count: usize) -> &mut Self {
+        self.push(role, text.repeat(count))
+    }
+
+    fn take(&mut self) -> Vec<Segment> {
+        std::mem::take(&mut self.0)
+    }
+}
+
+impl AnnotationList<'_> {
+    /// Lay out the message as a list of lines made of styled segments, without writing it
+    /// anywhere. This allows to render the message with custom frontends (e.g. GUI or TUI)
+    /// while reusing the layout of [`.show()`](AnnotationList::show), which is itself
+    /// implemented on top of this.
+    ///
+    /// Colors of the stylesheet are not used, but other settings are. Layout settings are
+    /// the default ones, see [`.render_events_with_options()`](AnnotationList::render_events_with_options).
+    /// ```rust
+    /// # use show_my_errors::{AnnotationList, RenderEvent, Stylesheet};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("hello.txt", "Hello world!");
+    /// list.warning(4..7, "punctuation problem", "you probably forgot a comma")?;
+    /// let events = list.render_events(&Stylesheet::monochrome());
+    /// assert!(matches!(events[0], RenderEvent::SeverityHeader(_)));
+    /// assert_eq!(events[0].segments()[0].text, "warning: punctuation problem");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn render_events(&self, stylesheet: &Stylesheet) -> Vec<RenderEvent> {
+        self.render_events_with_options(stylesheet, &RenderOptions::default())
+    }
+
+    /// Like [`.render_events()`](AnnotationList::render_events), but uses given layout
+    /// settings
+    pub fn render_events_with_options(
+        &self,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+    ) -> Vec<RenderEvent> {
+        let mut events = self.blocks_events(stylesheet, options, self.blocks());
+        if options.summary {
+            summary_events(&mut events, stylesheet, options, self.severity_counts());
+        }
+        events
+    }
+
+    /// Lay out the message in compact form: one `severity: header (file:line:col)` line per
+    /// annotation, without source snippets. Only the first line of the message is used.
+    pub(crate) fn compact_events(
+        &self,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+    ) -> Vec<RenderEvent> {
+        let mut segments = Segments::default();
+        let mut filename = self.filename.clone();
+        if options.normalize_separators {
+            filename = filename.replace('\\', "/");
+        }
+        self.blocks()
+            .map(|(idx, annotation)| {
+                let severity = StyleRole::Severity(annotation.severity);
+                segments.push(
+                    severity,
+                    stylesheet.labels.by_severity(&annotation.severity),
+                );
+                push_code(&mut segments, annotation);
+                let message = annotation
+                    .message()
+                    .and_then(|message| message.lines().next());
+                if let Some(message) = message {
+                    segments.push(severity, ": ").push(severity, message);
+                }
+                segments
+                    .push(StyleRole::LineNumber, " (")
+                    .push(
+                        StyleRole::Filename,
+                        self.location(options, &filename, idx, annotation),
+                    )
+                    .push(StyleRole::LineNumber, ")");
+                RenderEvent::SeverityHeader(segments.take())
+            })
+            .collect()
+    }
+
+    /// Lay out the message in GCC-style short form: one `file:line:col: severity: header`
+    /// line per annotation, without source snippets. Only the first line of the message is
+    /// used.
+    pub(crate) fn short_events(
+        &self,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+    ) -> Vec<RenderEvent> {
+        let mut filename = self.filename.clone();
+        if options.normalize_separators {
+            filename = filename.replace('\\', "/");
+        }
+        self.blocks()
+            .map(|(idx, annotation)| {
+                self.short_event(stylesheet, options, &filename, idx, annotation)
+            })
+            .collect()
+    }
+
+    /// Lay out a single annotation in GCC-style short form, see
+    /// [`.short_events()`](AnnotationList::short_events)
+    pub(crate) fn short_event(
+        &self,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+        filename: &str,
+        idx: usize,
+        annotation: &Annotation,
+    ) -> RenderEvent {
+        let mut segments = Segments::default();
+        let severity = StyleRole::Severity(annotation.severity);
+        segments
+            .push(
+                StyleRole::Filename,
+                self.location(options, filename, idx, annotation),
+            )
+            .push(StyleRole::Filename, ":")
+            .push(StyleRole::LineNumber, " ")
+            .push(
+                severity,
+                stylesheet.labels.by_severity(&annotation.severity),
+            );
+        push_code(&mut segments, annotation);
+        let message = annotation
+            .message()
+            .and_then(|message| message.lines().next());
+        if let Some(message) = message {
+            segments.push(severity, ": ").push(severity, message);
+        }
+        RenderEvent::SeverityHeader(segments.take())
+    }
+
+    /// Location of annotation: `file:line:col`, or `file:line` for line notes
+    fn location(
+        &self,
+        options: &RenderOptions,
+        filename: &str,
+        idx: usize,
+        annotation: &Annotation,
+    ) -> String {
+        if annotation.line_note {
+            format!("{}:{}", filename, idx + 1)
+        } else {
+            let column = self.lines[idx].display_column(annotation, options.column_encoding);
+            format!("{}:{}:{}", filename, idx + 1, column)
+        }
+    }
+
+    /// Lay out given blocks
+    pub(crate) fn blocks_events<'b>(
+        &self,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+        blocks: impl IntoIterator<Item = (usize, &'b Annotation)>,
+    ) -> Vec<RenderEvent> {
+        let mut events = vec![];
+        self.for_each_block_events(stylesheet, options, blocks, |block| events.extend(block));
+        events
+    }
+
+    /// Like [`.blocks_events()`](AnnotationList::blocks_events), but calls `f` with events of
+    /// each block separately instead of collecting them. Blank lines separating a block from
+    /// the previous one are included in its events.
+    pub(crate) fn for_each_block_events<'b>(
+        &self,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+        blocks: impl IntoIterator<Item = (usize, &'b Annotation)>,
+        mut f: impl FnMut(Vec<RenderEvent>),
+    ) {
+        let blocks: Vec<_> = blocks.into_iter().collect();
+        // Label column of each line with aligned labels: one space after the rightmost caret
+        let mut label_columns = HashMap::new();
+        if options.align_labels {
+            for &(idx, annotation) in &blocks {
+                if annotation.range().is_empty() {
+                    continue;
+                }
+                let (offset, count) = caret_columns(&self.lines[idx], annotation.range(), options);
+                let column = label_columns.entry(idx).or_insert(0);
+                *column = (*column).max(offset + count);
+            }
+        }
+        let linenr_width = self.linenr_width(options, &blocks);
+        let label_refs = self.label_refs(options);
+        for (block_idx, (idx, annotation)) in blocks.into_iter().enumerate() {
+            let mut events = vec![];
+            // Padding
+            if block_idx != 0 {
+                events.extend(iter::repeat(RenderEvent::Blank).take(options.separator_lines));
+            }
+            self.annotation_events(
+                &mut events,
+                stylesheet,
+                options,
+                idx,
+                annotation,
+                linenr_width,
+                label_columns.get(&idx).copied(),
+                &label_refs,
+            );
+            f(events);
+        }
+    }
+
+    /// Find the first use of each shared label among all annotations, in display order
+    pub(crate) fn label_refs(&self, options: &RenderOptions) -> LabelRefs<'_> {
+        let mut refs = LabelRefs::new();
+        if self.labels.is_empty() {
+            return refs;
+        }
+        for (idx, annotation) in self.blocks() {
+            if let Some(id) = &annotation.label_id {
+                refs.entry(id.as_str()).or_insert_with(|| {
+                    let column =
+                        self.lines[idx].display_column(annotation, options.column_encoding);
+                    (idx + 1, column, annotation)
+                });
+            }
+        }
+        refs
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn annotation_events(
+        &self,
+        events: &mut Vec<RenderEvent>,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+        idx: usize,
+        annotation: &Annotation,
+        linenr_width: usize,
+        label_column: Option<usize>,
+        label_refs: &LabelRefs<'_>,
+    ) {
+        let line = &self.lines[idx];
+        let range = annotation.range();
+        let severity = StyleRole::Severity(annotation.severity);
+        let nrcol_width = linenr_width + 2;
+        let mut segments = Segments::default();
+        let end_idx = self.end_line_index(idx, annotation);
+        let span_range = range.start - line.start..range.end - line.start;
+        let joined: String;
+        let span_text = if end_idx == idx {
+            line.content.get(span_range).unwrap_or_default()
+        } else {
+            joined = self.lines[idx..=end_idx]
+                .iter()
+                .map(|line| line.content())
+                .collect();
+            joined.get(span_range).unwrap_or_default()
+        };
+        let expand = |message: &str| {
+            if options.expand_span {
+                message.replace("{span}", span_text)
+            } else {
+                message.into()
+            }
+        };
+        // Annotation text, or shared label with a reference to its first use
+        let label = annotation
+            .label_id
+            .as_ref()
+            .and_then(|id| Some((id, self.labels.get(id)?)));
+        let text = match label {
+            Some((id, label)) => match label_refs.get(id.as_str()) {
+                Some(&(line, col, other)) if !std::ptr::eq(other, annotation) => {
+                    expand(&format!("{} (see {}:{})", label, line, col))
+                }
+                _ => expand(label),
+            },
+            None => expand(annotation.text.as_deref().unwrap_or_default()),
+        };
+
+        // Severity and header. Without header only the separator's trailing whitespace is
+        // omitted, e.g. `error:`.
+        segments.push(
+            severity,
+            stylesheet.labels.by_severity(&annotation.severity),
+        );
+        push_code(&mut segments, annotation);
+        if let Some(header) = &annotation.header {
+            segments
+                .push(severity, &options.header_separator)
+                .push(severity, expand(header));
+        } else {
+            segments.push(severity, options.header_separator.trim_end());
+        }
+        events.push(RenderEvent::SeverityHeader(segments.take()));
+
+        // Line numbers column & filename
+        if options.show_location {
+            segments
+                .push_n(StyleRole::LineNumber, " ", linenr_width + 1)
+                .push(StyleRole::LineNumber, "--> ");
+            let normalized;
+            let mut filename = self.filename.as_str();
+            if options.normalize_separators {
+                normalized = filename.replace('\\', "/");
+                filename = &normalized;
+            }
+            if stylesheet.directory.is_some() {
+                let is_separator = |c| c == '/' || (cfg!(windows) && c == '\\');
+                if let Some(sep_idx) = filename.rfind(is_separator) {
+                    segments.push(StyleRole::Directory, &filename[..=sep_idx]);
+                    filename = &filename[sep_idx + 1..];
+                }
+            }
+            let location = if annotation.line_note {
+                format!("{}:{}", filename, idx + 1)
+            } else {
+                format!(
+                    "{}:{}:{}",
+                    filename,
+                    idx + 1,
+                    line.display_column(annotation, options.column_encoding)
+                )
+            };
+            segments.push(StyleRole::Filename, location);
+            events.push(RenderEvent::LocationLine(segments.take()));
+        }
+        segments
+            .push_n(StyleRole::LineNumber, " ", nrcol_width)
+            .push(StyleRole::LineNumber, "|");
+        events.push(RenderEvent::Gutter(segments.take()));
+
+        // Secondary spans, in source order. The ones on earlier lines are shown before the
+        // annotation, the rest after it.
+        let mut secondary: Vec<_> = annotation
+            .secondary_spans()
+            .iter()
+            .map(|span| (self.line_index_for(span.range().start).unwrap_or(idx), span))
+            .collect();
+        secondary.sort_by_key(|(line_idx, span)| (*line_idx, span.range().start));
+        let split = secondary.partition_point(|(line_idx, _span)| *line_idx < idx);
+        let (secondary_before, secondary_after) = secondary.split_at(split);
+        self.secondary_events(events, options, secondary_before, linenr_width, None);
+        // Context lines are shown around the annotation, but not before lines already shown
+        let context_start = secondary_before
+            .last()
+            .map_or(0, |(line_idx, _span)| line_idx + 1)
+            .max(idx.saturating_sub(options.context_lines));
+        self.context_events(events, options, context_start..idx, linenr_width);
+        let context_start = secondary_after
+            .last()
+            .map_or(end_idx, |(line_idx, _span)| end_idx.max(*line_idx))
+            + 1;
+        let context_end = cmp::min(end_idx + options.context_lines + 1, self.lines.len());
+        let context_after = context_start..context_end.max(context_start);
+
+        if end_idx != idx {
+            self.multiline_events(
+                events,
+                stylesheet,
+                options,
+                idx..=end_idx,
+                annotation,
+                linenr_width,
+                &text,
+            );
+            self.secondary_events(events, options, secondary_after, linenr_width, None);
+            self.context_events(events, options, context_after, linenr_width);
+            self.note_events(events, stylesheet, options, annotation, linenr_width);
+            self.suggestion_events(events, stylesheet, options, annotation, linenr_width);
+            return;
+        }
+
+        // Column ruler in display columns, so digits line up with the content: tens (only if
+        // there are at least 10 columns) and units
+        let content_width: usize = line
+            .content
+            .strip_suffix('\n')
+            .unwrap_or(&line.content)
+            .chars()
+            .map(|c| options.display_width(c))
+            .sum();
+        if options.show_ruler && options.show_content && content_width != 0 {
+            let digits =
+                |digit: fn(usize) -> char| (1..=content_width).map(digit).collect::<String>();
+            let mut rows = vec![];
+            if content_width >= 10 {
+                rows.push(digits(|col| match col {
+                    1..=9 => ' ',
+                    col => char::from(b'0' + (col / 10 % 10) as u8),
+                }));
+            }
+            rows.push(digits(|col| char::from(b'0' + (col % 10) as u8)));
+            for row in rows {
+                segments
+                    .push_n(StyleRole::LineNumber, " ", nrcol_width)
+                    .push(StyleRole::LineNumber, "| ")
+                    .push(StyleRole::LineNumber, row);
+                events.push(RenderEvent::Ruler(segments.take()));
+            }
+        }
+
+        // Line number. Content line is omitted in caret-only mode, carets stay aligned as if
+        // it was there.
+        if options.show_content {
+            let linenr = format!("{:>width$}", idx + 1, width = linenr_width);
+            segments.push(StyleRole::LineNumber, " ");
+            let max_severity = line.annotations().iter().map(|ann| ann.severity).max();
+            match max_severity {
+                Some(max_severity) if options.linenr_by_severity => {
+                    segments.push(StyleRole::Severity(max_severity), linenr)
+                }
+                _ => segments.push(StyleRole::LineNumber, linenr),
+            };
+            segments.push(StyleRole::LineNumber, " |");
+
+            // Line content. Empty lines are rendered without trailing whitespace after the gutter.
+            // Tabs are expanded to spaces if tab width is set.
+            let content = line.content.strip_suffix('\n').unwrap_or(&line.content);
+            let expand_tabs = |text: &str, tab: &str| expand_tabs(text, tab, options);
+            if !content.is_empty() {
+                segments.push(StyleRole::LineNumber, " ");
+            }
+            let span_start = range.start - line.start;
+            let span_end = (range.end - line.start).min(content.len());
+            match (
+                content.get(..span_start),
+                content.get(span_start..span_end),
+                content.get(span_end..),
+            ) {
+                (Some(before), Some(span), Some(after))
+                    if !span.is_empty()
+                        && (stylesheet.highlight_span
+                            || stylesheet.visible_whitespace
+                            || options.underline_span) =>
+                {
+                    let span_role = if options.underline_span {
+                        StyleRole::Underline(annotation.severity)
+                    } else if stylesheet.highlight_span {
+                        severity
+                    } else {
+                        StyleRole::Content
+                    };
+                    segments.push(StyleRole::Content, expand_tabs(before, " "));
+                    if stylesheet.visible_whitespace {
+                        let (space, tab) = if stylesheet.ascii_only {
+                            ('.', ">")
+                        } else {
+                            ('·', "→")
+                        };
+                        let span = expand_tabs(&span.replace(' ', &space.to_string()), tab);
+                        segments.push(span_role, span.replace('\t', tab));
+                    } else {
+                        segments.push(span_role, expand_tabs(span, " "));
+                    }
+                    segments.push(StyleRole::Content, expand_tabs(after, " "));
+                }
+                _ => {
+                    segments.push(StyleRole::Content, expand_tabs(content, " "));
+                }
+            }
+            events.push(RenderEvent::SourceLine(segments.take()));
+        }
+
+        // Line numbers column
+        segments
+            .push_n(StyleRole::LineNumber, " ", nrcol_width)
+            .push(StyleRole::LineNumber, "|");
+
+        // Annotation. Continuation lines of multi-line text are aligned with its first line.
+        // Line notes have no carets, so their text starts right after the gutter. Underlined
+        // fragments have no carets either, their text starts under the fragment.
+        let mut text_lines = text.lines();
+        let content_len = line
+            .content
+            .strip_suffix('\n')
+            .unwrap_or(&line.content)
+            .len();
+        let caret = caret_role(stylesheet, annotation.severity);
+        // Annotation covering the whole line content gets a short marker instead of carets
+        // under every char
+        let full_line_marker = if stylesheet.ascii_only {
+            "^..."
+        } else {
+            "^…"
+        };
+        let full_line = options.succinct_full_line
+            && content_len > 1
+            && range.start == line.start
+            && range.end - line.start >= content_len;
+        let (caret_offset, mut caret_count) = caret_columns(line, annotation.range(), options);
+        if full_line {
+            caret_count = full_line_marker.chars().count();
+        }
+        let caret_end = caret_offset + caret_count;
+        let label_column = label_column.unwrap_or(caret_end).max(caret_end);
+        // Zero-length annotations get a caret only at the end of a non-empty line, pointing
+        // just past its last char
+        let at_eol =
+            range.start == range.end && content_len != 0 && range.start - line.start == content_len;
+        let has_carets = !annotation.line_note && (range.end != range.start || at_eol);
+        let show_text = annotation.line_note || has_carets;
+        let underline = options.underline_span
+            && options.show_content
+            && !annotation.line_note
+            && range.end != range.start;
+        let text_offset = if annotation.line_note {
+            1
+        } else if underline {
+            caret_offset + 1
+        } else {
+            label_column + 2
+        };
+        let caret_row = !underline || !text.is_empty() || options.show_range;
+        if annotation.line_note {
+            if let Some(text) = text_lines.next() {
+                segments.push(severity, " ").push(severity, text);
+            }
+        } else if underline {
+            if let Some(text) = text_lines.next() {
+                segments
+                    .push_n(severity, " ", caret_offset + 1)
+                    .push(severity, text);
+            }
+        } else if has_carets {
+            segments.push_n(severity, " ", caret_offset + 1);
+            if full_line {
+                segments.push(caret, full_line_marker);
+            } else if stylesheet.bold_caret_edges && caret_count > 0 {
+                let edge = StyleRole::CaretEdge(annotation.severity);
+                segments
+                    .push(edge, "^")
+                    .push_n(
+                        StyleRole::CaretMiddle(annotation.severity),
+                        "^",
+                        caret_count.saturating_sub(2),
+                    )
+                    .push_n(edge, "^", cmp::min(caret_count - 1, 1));
+            } else {
+                segments.push_n(caret, "^", caret_count);
+            }
+            if let Some(text) = text_lines.next() {
+                segments
+                    .push_n(severity, " ", label_column - caret_end + 1)
+                    .push(severity, text);
+            }
+        }
+        if options.show_range {
+            segments.push(
+                StyleRole::LineNumber,
+                format!(" [{}..{}]", range.start, range.end),
+            );
+        }
+        if caret_row {
+            events.push(RenderEvent::CaretLine(segments.take()));
+        } else {
+            segments.take();
+        }
+        if show_text {
+            for text in text_lines {
+                segments
+                    .push_n(StyleRole::LineNumber, " ", nrcol_width)
+                    .push(StyleRole::LineNumber, "|")
+                    .push_n(severity, " ", text_offset)
+                    .push(severity, text);
+                events.push(RenderEvent::TextLine(segments.take()));
+            }
+        }
+        if let Some(replacement) = &annotation.replacement {
+            for text in replacement.lines() {
+                segments
+                    .push_n(StyleRole::LineNumber, " ", nrcol_width)
+                    .push(StyleRole::LineNumber, "|")
+                    .push_n(severity, " ", caret_offset + 1)
+                    .push(severity, text);
+                events.push(RenderEvent::TextLine(segments.take()));
+            }
+        }
+        self.secondary_events(events, options, secondary_after, linenr_width, Some(idx));
+        self.context_events(events, options, context_after, linenr_width);
+        self.note_events(events, stylesheet, options, annotation, linenr_width);
+        self.suggestion_events(events, stylesheet, options, annotation, linenr_width);
+    }
+
+    /// Lay out suggestions of an annotation, rustc-style: `help: replace with ...` followed by
+    /// the patched line, with the new text marked by `~` (`+` for insertions). For removals
+    /// the original line is shown, with the removed text marked by `-`.
+    fn suggestion_events(
+        &self,
+        events: &mut Vec<RenderEvent>,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+        annotation: &Annotation,
+        linenr_width: usize,
+    ) {
+        let nrcol_width = linenr_width + 2;
+        let help = StyleRole::Severity(Severity::Help);
+        let mut segments = Segments::default();
+        let width = |text: &str| {
+            text.chars()
+                .map(|c| options.display_width(c))
+                .sum::<usize>()
+        };
+        for suggestion in &annotation.suggestions {
+            let range = suggestion.range();
+            let replacement = suggestion.replacement.as_str();
+            let idx = match self.line_index_for(range.start) {
+                Ok(idx) if !(range.is_empty() && replacement.is_empty()) => idx,
+                _ => continue,
+            };
+            let line = &self.lines[idx];
+            let content = line.content.strip_suffix('\n').unwrap_or(&line.content);
+            let start = (range.start - line.start).min(content.len());
+            let end = (range.end - line.start).min(content.len());
+            let (before, removed, after) = match (
+                content.get(..start),
+                content.get(start..end),
+                content.get(end..),
+            ) {
+                (Some(before), Some(removed), Some(after)) => (before, removed, after),
+                _ => continue,
+            };
+            let (message, quoted, patched, marked, marker) = if replacement.is_empty() {
+                ("remove", removed, content.to_owned(), start..end, "-")
+            } else {
+                let patched = format!("{}{}{}", before, replacement, after);
+                let marked = start..start + replacement.len();
+                if range.is_empty() {
+                    ("insert", replacement, patched, marked, "+")
+                } else {
+                    ("replace with", replacement, patched, marked, "~")
+                }
+            };
+            segments
+                .push(help, stylesheet.labels.by_note_kind(NoteKind::Help))
+                .push(help, &options.header_separator)
+                .push(help, message);
+            if !quoted.contains('\n') {
+                segments.push(help, format!(" `{}`", quoted));
+            }
+            events.push(RenderEvent::TextLine(segments.take()));
+            segments
+                .push_n(StyleRole::LineNumber, " ", nrcol_width)
+                .push(StyleRole::LineNumber, "|");
+            events.push(RenderEvent::Gutter(segments.take()));
+
+            let mut line_start = 0;
+            for (line_idx, text) in patched.split('\n').enumerate() {
+                let line_end = line_start + text.len();
+                segments.push(
+                    StyleRole::LineNumber,
+                    format!(" {:>width$} |", idx + line_idx + 1, width = linenr_width),
+                );
+                if !text.is_empty() {
+                    segments
+                        .push(StyleRole::Content, " ")
+                        .push(StyleRole::Content, expand_tabs(text, " ", options));
+                }
+                events.push(RenderEvent::SourceLine(segments.take()));
+                let mark_start = marked.start.clamp(line_start, line_end) - line_start;
+                let mark_end = marked.end.clamp(line_start, line_end) - line_start;
+                if mark_start < mark_end {
+                    segments
+                        .push_n(StyleRole::LineNumber, " ", nrcol_width)
+                        .push(StyleRole::LineNumber, "|")
+                        .push_n(help, " ", width(&text[..mark_start]) + 1)
+                        .push_n(help, marker, width(&text[mark_start..mark_end]));
+                    events.push(RenderEvent::CaretLine(segments.take()));
+                }
+                line_start = line_end + 1;
+            }
+        }
+    }
+
+    /// Lay out notes and help messages of an annotation, rustc-style: `= note: text`, with
+    /// continuation lines of the text aligned with its first line. Documentation link goes
+    /// last, as `= note: see <url>`.
+    fn note_events(
+        &self,
+        events: &mut Vec<RenderEvent>,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+        annotation: &Annotation,
+        linenr_width: usize,
+    ) {
+        if annotation.notes.is_empty() && annotation.url.is_none() {
+            return;
+        }
+        let nrcol_width = linenr_width + 2;
+        let mut segments = Segments::default();
+        segments
+            .push_n(StyleRole::LineNumber, " ", nrcol_width)
+            .push(StyleRole::LineNumber, "|");
+        events.push(RenderEvent::Gutter(segments.take()));
+        for note in &annotation.notes {
+            let label = stylesheet.labels.by_note_kind(note.kind);
+            let role = StyleRole::Severity(note.kind.into());
+            let mut text_lines = note.text.lines();
+            segments
+                .push_n(StyleRole::LineNumber, " ", nrcol_width)
+                .push(StyleRole::LineNumber, "= ")
+                .push(role, label)
+                .push(role, &options.header_separator)
+                .push(StyleRole::Content, text_lines.next().unwrap_or_default());
+            events.push(RenderEvent::TextLine(segments.take()));
+            let indent = label.chars().count() + options.header_separator.chars().count() + 2;
+            for text in text_lines {
+                segments
+                    .push_n(StyleRole::LineNumber, " ", nrcol_width + indent)
+                    .push(StyleRole::Content, text);
+                events.push(RenderEvent::TextLine(segments.take()));
+            }
+        }
+        if let Some(url) = &annotation.url {
+            let role = StyleRole::Severity(Severity::Note);
+            segments
+                .push_n(StyleRole::LineNumber, " ", nrcol_width)
+                .push(StyleRole::LineNumber, "= ")
+                .push(role, &stylesheet.labels.note)
+                .push(role, &options.header_separator)
+                .push(StyleRole::Content, "see ")
+                .push(StyleRole::Link, url);
+            events.push(RenderEvent::TextLine(segments.take()));
+        }
+    }
+
+    /// Lay out secondary spans, given along with indices of their lines in source order.
+    /// Spans are marked with `-` under the line they're on, which is shown before the first
+    /// of them unless it's `shown_line`, i.e. the line shown just before.
+    fn secondary_events(
+        &self,
+        events: &mut Vec<RenderEvent>,
+        options: &RenderOptions,
+        spans: &[(usize, &SecondarySpan)],
+        linenr_width: usize,
+        mut shown_line: Option<usize>,
+    ) {
+        let nrcol_width = linenr_width + 2;
+        let mut segments = Segments::default();
+        for &(idx, span) in spans {
+            let line = &self.lines[idx];
+            if shown_line != Some(idx) {
+                self.context_events(events, options, idx..idx + 1, linenr_width);
+            }
+            shown_line = Some(idx);
+            let (offset, count) = caret_columns(line, span.range(), options);
+            let text = span.text.as_deref().unwrap_or_default();
+            let mut text_lines = text.lines();
+            segments
+                .push_n(StyleRole::LineNumber, " ", nrcol_width)
+                .push(StyleRole::LineNumber, "|")
+                .push_n(StyleRole::LineNumber, " ", offset + 1)
+                .push_n(StyleRole::LineNumber, "-", count);
+            if let Some(text) = text_lines.next() {
+                segments
+                    .push(StyleRole::LineNumber, " ")
+                    .push(StyleRole::LineNumber, text);
+            }
+            events.push(RenderEvent::CaretLine(segments.take()));
+            for text in text_lines {
+                segments
+                    .push_n(StyleRole::LineNumber, " ", nrcol_width)
+                    .push(StyleRole::LineNumber, "|")
+                    .push_n(StyleRole::LineNumber, " ", offset + count + 2)
+                    .push(StyleRole::LineNumber, text);
+                events.push(RenderEvent::TextLine(segments.take()));
+            }
+        }
+    }
+
+    /// Lay out source lines without any annotations, e.g. context around the annotated line
+    fn context_events(
+        &self,
+        events: &mut Vec<RenderEvent>,
+        options: &RenderOptions,
+        lines: Range<usize>,
+        linenr_width: usize,
+    ) {
+        if !options.show_content {
+            return;
+        }
+        let mut segments = Segments::default();
+        for (idx, line) in self.lines[lines.clone()].iter().enumerate() {
+            let content = line.content.strip_suffix('\n').unwrap_or(&line.content);
+            segments.push(
+                StyleRole::LineNumber,
+                format!(" {:>width$} |", lines.start + idx + 1, width = linenr_width),
+            );
+            if !content.is_empty() {
+                segments
+                    .push(StyleRole::Content, " ")
+                    .push(StyleRole::Content, expand_tabs(content, " ", options));
+            }
+            events.push(RenderEvent::SourceLine(segments.take()));
+        }
+    }
+
+    /// Lay out source lines and carets of a multi-line annotation, rustc-style: the start
+    /// and the end of the span are connected by a line drawn along the gutter.
+    #[allow(clippy::too_many_arguments)]
+    fn multiline_events(
+        &self,
+        events: &mut Vec<RenderEvent>,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+        lines: RangeInclusive<usize>,
+        annotation: &Annotation,
+        linenr_width: usize,
+        text: &str,
+    ) {
+        let range = annotation.range();
+        let severity = StyleRole::Severity(annotation.severity);
+        let caret = caret_role(stylesheet, annotation.severity);
+        let nrcol_width = linenr_width + 2;
+        let mut segments = Segments::default();
+        // Display column of the offset, clamped to the line content
+        let column = |idx: usize, offset: usize| {
+            let line = &self.lines[idx];
+            let content = line.content.strip_suffix('\n').unwrap_or(&line.content);
+            let end = (offset - line.start).min(content.len());
+            content.get(..end).map_or(end, |s| {
+                s.chars().map(|c| options.display_width(c)).sum::<usize>()
+            })
+        };
+        let (start_idx, end_idx) = (*lines.start(), *lines.end());
+        let start_column = column(start_idx, range.start);
+        // Caret goes under the last char, so its column is taken at the start of that char
+        let end_line = &self.lines[end_idx];
+        let mut last = range.end - 1;
+        while !end_line.content.is_char_boundary(last - end_line.start) {
+            last -= 1;
+        }
+        let end_column = column(end_idx, last);
+        let mut text_lines = text.lines();
+
+        for idx in lines {
+            let line = &self.lines[idx];
+            if options.show_content {
+                let content = line.content.strip_suffix('\n').unwrap_or(&line.content);
+                segments
+                    .push(
+                        StyleRole::LineNumber,
+                        format!(" {:>width$} |", idx + 1, width = linenr_width),
+                    )
+                    .push(severity, if idx == start_idx { "  " } else { " |" });
+                if !content.is_empty() {
+                    segments
+                        .push(StyleRole::Content, " ")
+                        .push(StyleRole::Content, expand_tabs(content, " ", options));
+                }
+                events.push(RenderEvent::SourceLine(segments.take()));
+            }
+            if idx == start_idx {
+                segments
+                    .push_n(StyleRole::LineNumber, " ", nrcol_width)
+                    .push(StyleRole::LineNumber, "|")
+                    .push(severity, "  ")
+                    .push_n(severity, "_", start_column + 1)
+                    .push(caret, "^");
+                events.push(RenderEvent::CaretLine(segments.take()));
+            }
+        }
+
+        segments
+            .push_n(StyleRole::LineNumber, " ", nrcol_width)
+            .push(StyleRole::LineNumber, "|")
+            .push(severity, " |")
+            .push_n(severity, "_", end_column + 1)
+            .push(caret, "^");
+        if let Some(text) = text_lines.next() {
+            segments.push(severity, " ").push(severity, text);
+        }
+        if options.show_range {
+            segments.push(
+                StyleRole::LineNumber,
+                format!(" [{}..{}]", range.start, range.end),
+            );
+        }
+        events.push(RenderEvent::CaretLine(segments.take()));
+        for text in text_lines {
+            segments
+                .push_n(StyleRole::LineNumber, " ", nrcol_width)
+                .push(StyleRole::LineNumber, "|")
+                .push_n(severity, " ", end_column + 5)
+                .push(severity, text);
+            events.push(RenderEvent::TextLine(segments.take()));
+        }
+    }
+
+    /// Width of the widest line number among shown lines. All line numbers are right-aligned
+    /// to it, so the gutter has the same width in every block.
+    pub(crate) fn linenr_width(
+        &self,
+        options: &RenderOptions,
+        blocks: &[(usize, &Annotation)],
+    ) -> usize {
+        let last_line = blocks
+            .iter()
+            .flat_map(|&(idx, annotation)| {
+                let secondary = annotation
+                    .secondary_spans()
+                    .iter()
+                    .filter_map(|span| self.line_index_for(span.range().start).ok());
+                iter::once(self.end_line_index(idx, annotation)).chain(secondary)
+            })
+            .max()
+            .unwrap_or(0);
+        let last_context_line = cmp::min(
+            last_line + options.context_lines,
+            self.lines.len().saturating_sub(1),
+        );
+        (last_line.max(last_context_line) + 1).to_string().len()
+    }
+}
+
+/// Append `[code]` after severity name if annotation has a code
+fn push_code(segments: &mut Segments, annotation: &Annotation) {
+    if let Some(code) = &annotation.code {
+        let severity = StyleRole::Severity(annotation.severity);
+        segments
+            .push(severity, "[")
+            .push(StyleRole::Code(annotation.severity), code)
+            .push(severity, "]");
+    }
+}
+
+/// Append summary footer after `events`, see [`RenderOptions::summary`]
+pub(crate) fn summary_events(
+    events: &mut Vec<RenderEvent>,
+    stylesheet: &Stylesheet,
+    options: &RenderOptions,
+    counts: SeverityCounts,
+) {
+    if let Some((severity, summary)) = counts.summary(&stylesheet.labels) {
+        if !events.is_empty() {
+            events.extend(iter::repeat(RenderEvent::Blank).take(options.separator_lines));
+        }
+        let role = StyleRole::Severity(severity);
+        let mut segments = Segments::default();
+        segments
+            .push(role, stylesheet.labels.by_severity(&severity))
+            .push(role, &options.header_separator)
+            .push(role, summary);
+        events.push(RenderEvent::SeverityHeader(segments.take()));
+    }
+}
+
+/// Show tabs in `text` as `tab` padded with spaces to the tab width, if it's set
+fn expand_tabs(text: &str, tab: &str, options: &RenderOptions) -> String {
+    match options.tab_width {
+        Some(tab_width) if text.contains('\t') => {
+            let padding = " ".repeat(tab_width.saturating_sub(1));
+            text.replace('\t', &format!("{}{}", tab, padding))
+        }
+        _ => text.to_owned(),
+    }
+}
+
+/// Offset of the carets under `range` from the start of the line and their count, both in
+/// display columns. Carets never extend past the end of the line content, even if the range
+/// somehow does.
+fn caret_columns(
+    line: &AnnotatedLine<'_>,
+    range: &Range<usize>,
+    options: &RenderOptions,
+) -> (usize, usize) {
+    let content = line.content.strip_suffix('\n').unwrap_or(&line.content);
+    let width = |start: usize, end: usize| {
+        let end = end.min(content.len());
+        let start = start.min(end);
+        content.get(start..end).map_or(end - start, |s| {
+            s.chars().map(|c| options.display_width(c)).sum::<usize>()
+        })
+    };
+    let offset = width(0, range.start - line.start);
+    let count = width(range.start - line.start, range.end - line.start).max(1);
+    (offset, count)
+}
+
+/// Role of carets under a fragment annotated with given severity
+fn caret_role(stylesheet: &Stylesheet, severity: Severity) -> StyleRole {
+    if stylesheet.underline_carets {
+        StyleRole::Caret(severity)
+    } else {
+        StyleRole::Severity(severity)
+    }
+}
+
+/// Style of text with given role: its color with attributes changed by the role
+pub(crate) fn role_spec(stylesheet: &Stylesheet, role: StyleRole) -> ColorSpec {
+    let mut spec = stylesheet.by_role(role).clone();
+    match role {
+        StyleRole::Underline(_) | StyleRole::Caret(_) => {
+            spec.set_underline(true);
+        }
+        StyleRole::CaretEdge(_) => {
+            spec.set_bold(true)
+                .set_underline(stylesheet.underline_carets);
+        }
+        StyleRole::CaretMiddle(_) => {
+            spec.set_bold(false)
+                .set_underline(stylesheet.underline_carets);
+        }
+        _ => (),
+    }
+    spec
+}
+
+/// Write rendered lines to the output, colorizing them using the stylesheet
+pub(crate) fn write_events<'e>(
+    stream: &mut impl Output,
+    stylesheet: &Stylesheet,
+    options: &RenderOptions,
+    events: impl IntoIterator<Item = &'e RenderEvent>,
+) -> io::Result<()> {
+    let mut current_role = None;
+    let mut events = events.into_iter().peekable();
+    if events.peek().is_none() {
+        return Ok(());
+    }
+    for event in events {
+        if let RenderEvent::Blank = event {
+            stream.reset()?;
+            current_role = None;
+        } else if !options.indent.is_empty() {
+            if current_role.is_some() {
+                stream.reset()?;
+                current_role = None;
+            }
+            stream.write_str(&options.indent)?;
+        }
+        for segment in event.segments() {
+            if current_role != Some(segment.role) {
+                stream.set_color(&role_spec(stylesheet, segment.role))?;
+                current_role = Some(segment.role);
+            }
+            if segment.role == StyleRole::Link && stylesheet.hyperlinks && stream.supports_color() {
+                stream.write_str(&format!("\x1b]8;;{}\x1b\\", segment.text))?;
+                stream.write_str(&segment.text)?;
+                stream.write_str("\x1b]8;;\x1b\\")?;
+                continue;
+            }
+            stream.write_str(&segment.text)?;
+        }
+        stream.write_str("\n")?;
+    }
+    stream.reset()
+}
@@ -0,0 +1,301 @@
+use crate::{
+    output::{Output, Stream},
+    render::{summary_events, write_events},
+    Annotation, AnnotationList, Labels, RenderEvent, RenderOptions, Severity, Stylesheet,
+};
+use std::{
+    io::{self, Write},
+    iter,
+};
+use termcolor::WriteColor;
+
+/// Number of annotations of each severity, e.g. for a summary footer, see
+/// [`RenderOptions::summary`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SeverityCounts {
+    /// Number of [`Severity::Help`] annotations
+    pub helps: usize,
+    /// Number of [`Severity::Note`] annotations
+    pub notes: usize,
+    /// Number of [`Severity::Info`] annotations
+    pub infos: usize,
+    /// Number of [`Severity::Warning`] annotations
+    pub warnings: usize,
+    /// Number of [`Severity::Error`] annotations
+    pub errors: usize,
+    /// Number of [`Severity::Custom`] annotations of any name
+    pub custom: usize,
+}
+
+impl SeverityCounts {
+    pub(crate) fn count<'b>(&mut self, annotations: impl IntoIterator<Item = &'b Annotation>) {
+        for annotation in annotations {
+            match annotation.severity {
+                Severity::Help => self.helps += 1,
+                Severity::Note => self.notes += 1,
+                Severity::Info => self.infos += 1,
+                Severity::Warning => self.warnings += 1,
+                Severity::Error => self.errors += 1,
+                Severity::Custom { .. } => self.custom += 1,
+            }
+        }
+    }
+
+    /// Severity and text of the summary footer using wording from `labels`, or `None` if
+    /// there are neither errors nor warnings
+    /// ```rust
+    /// # use show_my_errors::{AnnotationList, Labels, Severity};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("hello.txt", "Hello world!");
+    /// list.error(0..5, "unknown word", None)?
+    ///     .warning(5..6, "missing comma", None)?
+    ///     .warning(11..12, "too excited", None)?;
+    /// let counts = list.severity_counts();
+    /// assert_eq!((counts.errors, counts.warnings), (1, 2));
+    /// assert_eq!(
+    ///     counts.summary(&Labels::default()),
+    ///     Some((
+    ///         Severity::Error,
+    ///         "aborting due to 1 previous error; 2 warnings emitted".into()
+    ///     ))
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn summary(&self, labels: &Labels) -> Option<(Severity, String)> {
+        let format = |template: &str, count: usize| {
+            template
+                .replace("{count}", &count.to_string())
+                .replace("{s}", if count == 1 { "" } else { "s" })
+        };
+        let mut parts = vec![];
+        if self.errors != 0 {
+            parts.push(format(&labels.errors_summary, self.errors));
+        }
+        if self.warnings != 0 {
+            parts.push(format(&labels.warnings_summary, self.warnings));
+        }
+        let severity = if self.errors != 0 {
+            Severity::Error
+        } else {
+            Severity::Warning
+        };
+        Some((severity, parts.join("; "))).filter(|_| !parts.is_empty())
+    }
+}
+
+/// Annotations for several files, shown together. Files are shown in order of their names,
+/// files without annotations are skipped.
+/// ```rust
+/// # use show_my_errors::{AnnotationList, MultiFileReport};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut main = AnnotationList::new("src/main.rs", "fn main() {}");
+/// main.warning(3..7, "function is never used", None)?;
+/// let mut lib = AnnotationList::new("src/lib.rs", "mod foo;");
+/// lib.error(4..7, "file not found for module `foo`", None)?;
+/// let mut report = MultiFileReport::new();
+/// report.push(main).push(lib);
+/// let output = report.to_string()?;
+/// assert!(output.find("src/lib.rs").unwrap() < output.find("src/main.rs").unwrap());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MultiFileReport<'a> {
+    lists: Vec<AnnotationList<'a>>,
+}
+
+impl<'a> MultiFileReport<'a> {
+    /// Create an empty report
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add annotations for one more file
+    pub fn push(&mut self, list: AnnotationList<'a>) -> &mut Self {
+        self.lists.push(list);
+        self
+    }
+
+    /// Copy borrowed sources of all files into the report, see
+    /// [`AnnotationList::into_owned`]
+    pub fn into_owned(self) -> MultiFileReport<'static> {
+        MultiFileReport {
+            lists: self
+                .lists
+                .into_iter()
+                .map(AnnotationList::into_owned)
+                .collect(),
+        }
+    }
+
+    /// Get annotation lists of all files, in the order they were added
+    pub fn lists(&self) -> &[AnnotationList<'a>] {
+        &self.lists
+    }
+
+    /// Count annotations of all files by severity
+    pub fn severity_counts(&self) -> SeverityCounts {
+        let mut counts = SeverityCounts::default();
+        for list in &self.lists {
+            counts.count(list.annotations());
+        }
+        counts
+    }
+
+    /// Merge lists with the same filename into the first of them, so their annotations are
+    /// shown as one file. Lists are only merged if their sources are the same; lists with
+    /// other sources are left as is. Returns the number of lists merged away.
+    /// ```rust
+    /// # use show_my_errors::{AnnotationList, MultiFileReport};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let source = "let x = 1;";
+    /// let mut first = AnnotationList::new("a.rs", source);
+    /// first.warning(4..5, "unused variable", None)?;
+    /// let mut second = AnnotationList::new("a.rs", source);
+    /// second.error(8..9, "mismatched types", None)?;
+    /// let mut report: MultiFileReport = vec![first, second].into_iter().collect();
+    /// assert_eq!(report.coalesce(), 1);
+    /// assert_eq!(report.lists().len(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn coalesce(&mut self) -> usize {
+        let mut merged: Vec<AnnotationList<'a>> = Vec::with_capacity(self.lists.len());
+        let mut count = 0;
+        for list in self.lists.drain(..) {
+            let target = merged.iter_mut().find(|target| {
+                target.filename == list.filename
+                    && target.lines.len() == list.lines.len()
+                    && target
+                        .lines
+                        .iter()
+                        .zip(&list.lines)
+                        .all(|(a, b)| a.content == b.content)
+            });
+            match target {
+                Some(target) => {
+                    for (target_line, line) in target.lines.iter_mut().zip(list.lines) {
+                        target_line.annotations.extend(line.annotations);
+                    }
+                    for (id, text) in list.labels {
+                        target.labels.entry(id).or_insert(text);
+                    }
+                    count += 1;
+                }
+                None => merged.push(list),
+            }
+        }
+        self.lists = merged;
+        count
+    }
+
+    /// Lists in display order: sorted by filename, keeping the order of lists with the same
+    /// filename
+    pub(crate) fn sorted_lists(&self) -> Vec<&AnnotationList<'a>> {
+        let mut lists: Vec<_> = self.lists.iter().collect();
+        lists.sort_by(|a, b| a.filename.cmp(&b.filename));
+        lists
+    }
+
+    /// Lay out the report, see [`AnnotationList::render_events`]. Files are separated the
+    /// same way as annotations inside a file.
+    pub fn render_events(&self, stylesheet: &Stylesheet) -> Vec<RenderEvent> {
+        self.render_events_with_options(stylesheet, &RenderOptions::default())
+    }
+
+    /// Like [`.render_events()`](MultiFileReport::render_events), but uses given layout
+    /// settings
+    pub fn render_events_with_options(
+        &self,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+    ) -> Vec<RenderEvent> {
+        let mut events = vec![];
+        for list in self.sorted_lists() {
+            let list_events = list.blocks_events(stylesheet, options, list.blocks());
+            if list_events.is_empty() {
+                continue;
+            }
+            if !events.is_empty() {
+                events.extend(iter::repeat(RenderEvent::Blank).take(options.separator_lines));
+            }
+            events.extend(list_events);
+        }
+        if options.summary {
+            summary_events(&mut events, stylesheet, options, self.severity_counts());
+        }
+        events
+    }
+
+    fn show_output(
+        &self,
+        stream: &mut impl Output,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        write_events(
+            stream,
+            stylesheet,
+            options,
+            &self.render_events_with_options(stylesheet, options),
+        )
+    }
+
+    /// Print the report to stream using given stylesheet, see [`AnnotationList::show`]
+    pub fn show<W: Write + WriteColor>(
+        &self,
+        stream: W,
+        stylesheet: &Stylesheet,
+    ) -> io::Result<()> {
+        self.show_with_options(stream, stylesheet, &RenderOptions::default())
+    }
+
+    /// Like [`.show()`](MultiFileReport::show), but uses given layout settings
+    pub fn show_with_options<W: Write + WriteColor>(
+        &self,
+        stream: W,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        self.show_output(&mut Stream(stream), stylesheet, options)
+    }
+
+    /// Append monochrome report to an existing [`String`], see
+    /// [`AnnotationList::render_into`]
+    pub fn render_into(&self, buf: &mut String, stylesheet: &Stylesheet) {
+        self.render_into_with_options(buf, stylesheet, &RenderOptions::default())
+    }
+
+    /// Like [`.render_into()`](MultiFileReport::render_into), but uses given layout settings
+    pub fn render_into_with_options(
+        &self,
+        buf: &mut String,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+    ) {
+        self.show_output(buf, stylesheet, options)
+            .expect("writing to String can't fail")
+    }
+
+    /// "Print" monochrome report to [`String`]
+    pub fn to_string(&self) -> io::Result<String> {
+        self.to_string_with_options(&RenderOptions::default())
+    }
+
+    /// Like [`.to_string()`](MultiFileReport::to_string), but uses given layout settings
+    pub fn to_string_with_options(&self, options: &RenderOptions) -> io::Result<String> {
+        let mut buf = String::new();
+        self.show_output(&mut buf, &Stylesheet::monochrome(), options)?;
+        Ok(buf)
+    }
+}
+
+impl<'a> iter::FromIterator<AnnotationList<'a>> for MultiFileReport<'a> {
+    fn from_iter<T: IntoIterator<Item = AnnotationList<'a>>>(iter: T) -> Self {
+        Self {
+            lists: iter.into_iter().collect(),
+        }
+    }
+}
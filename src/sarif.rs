@@ -0,0 +1,120 @@
+use crate::{json::escape_json, AnnotationList, MultiFileReport, Severity};
+
+fn sarif_level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Help | Severity::Note | Severity::Info => "note",
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+        Severity::Custom { .. } => sarif_level(severity.builtin()),
+    }
+}
+
+/// Rules referenced by results: codes in order of first use, with the first documentation
+/// link given for them
+#[derive(Default)]
+struct Rules(Vec<(String, Option<String>)>);
+
+impl Rules {
+    fn add(&mut self, code: &str, url: Option<&str>) {
+        match self.0.iter_mut().find(|(id, _url)| id == code) {
+            Some((_id, help_uri)) => {
+                if help_uri.is_none() {
+                    *help_uri = url.map(Into::into);
+                }
+            }
+            None => self.0.push((code.into(), url.map(Into::into))),
+        }
+    }
+}
+
+/// Wrap results into SARIF log with a single run
+fn sarif_log(results: &[String], rules: &Rules) -> String {
+    let rules: Vec<_> = rules
+        .0
+        .iter()
+        .map(|(id, help_uri)| match help_uri {
+            Some(help_uri) => format!(
+                r#"{{"id":"{}","helpUri":"{}"}}"#,
+                escape_json(id),
+                escape_json(help_uri)
+            ),
+            None => format!(r#"{{"id":"{}"}}"#, escape_json(id)),
+        })
+        .collect();
+    let rules = if rules.is_empty() {
+        String::new()
+    } else {
+        format!(r#","rules":[{}]"#, rules.join(","))
+    };
+    format!(
+        concat!(
+            r#"{{"$schema":"https://json.schemastore.org/sarif-2.1.0.json","version":"2.1.0","#,
+            r#""runs":[{{"tool":{{"driver":{{"name":"{}"{}}}}},"columnKind":"unicodeCodePoints","#,
+            r#""results":[{}]}}]}}"#,
+        ),
+        env!("CARGO_PKG_NAME"),
+        rules,
+        results.join(","),
+    )
+}
+
+impl AnnotationList<'_> {
+    /// Serialize annotations to [SARIF 2.1.0](https://sarifweb.azurewebsites.net/) log with
+    /// a single run and one result per annotation, e.g. for GitHub code scanning. Columns are
+    /// counted in Unicode code points. [Message](crate::Annotation::message) of the annotation
+    /// is used as the result message, its [code](crate::Annotation::code) as the
+    /// rule id, and its [documentation link](crate::Annotation::url) as help URI of the rule.
+    /// `\` in filename is replaced with `/`, since artifact location must be a URI.
+    ///
+    /// Requires `sarif` feature.
+    pub fn to_sarif(&self) -> String {
+        let mut results = vec![];
+        let mut rules = Rules::default();
+        self.sarif_results(&mut results, &mut rules);
+        sarif_log(&results, &rules)
+    }
+
+    fn sarif_results(&self, results: &mut Vec<String>, rules: &mut Rules) {
+        let uri = escape_json(&self.filename.replace('\\', "/"));
+        for (idx, annotation) in self.blocks() {
+            let line = &self.lines[idx];
+            let end_idx = self.end_line_index(idx, annotation);
+            let range = annotation.range();
+            let message = annotation.message().unwrap_or_default();
+            let rule_id = annotation.code.as_ref().map_or_else(String::new, |code| {
+                rules.add(code, annotation.url.as_deref());
+                format!(r#""ruleId":"{}","#, escape_json(code))
+            });
+            results.push(format!(
+                concat!(
+                    r#"{{{}"level":"{}","message":{{"text":"{}"}},"locations":[{{"#,
+                    r#""physicalLocation":{{"artifactLocation":{{"uri":"{}"}},"#,
+                    r#""region":{{"startLine":{},"startColumn":{},"endLine":{},"endColumn":{}}}}}}}]}}"#,
+                ),
+                rule_id,
+                sarif_level(annotation.severity),
+                escape_json(message),
+                uri,
+                idx + 1,
+                line.char_column(range.start),
+                end_idx + 1,
+                self.lines[end_idx].char_column(range.end),
+            ));
+        }
+    }
+}
+
+impl MultiFileReport<'_> {
+    /// Serialize annotations of all files to SARIF log with a single run, see
+    /// [`AnnotationList::to_sarif`]. Files are ordered by their names.
+    ///
+    /// Requires `sarif` feature.
+    pub fn to_sarif(&self) -> String {
+        let mut results = vec![];
+        let mut rules = Rules::default();
+        for list in self.sorted_lists() {
+            list.sarif_results(&mut results, &mut rules);
+        }
+        sarif_log(&results, &rules)
+    }
+}
@@ -0,0 +1,66 @@
+use std::ops::{Range, RangeInclusive};
+
+/// Byte range of an annotation. All methods accepting ranges take `impl Into<Span>`, so you can
+/// pass [`Range`], [`RangeInclusive`] or a `Span` created from start and length.
+/// ```rust
+/// # use show_my_errors::Span;
+/// assert_eq!(Span::new(4, 3), Span::from(4..7));
+/// assert_eq!(Span::inclusive(4..=6), Span::from(4..7));
+/// assert_eq!(Span::from(4..=6), Span::from(4..7));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// Offset of the first byte
+    pub start: usize,
+    /// Offset after the last byte
+    pub end: usize,
+}
+
+impl Span {
+    /// Create a span from its start and length
+    pub fn new(start: usize, len: usize) -> Self {
+        Self {
+            start,
+            end: start.saturating_add(len),
+        }
+    }
+
+    /// Create a span from an inclusive range
+    pub fn inclusive(range: RangeInclusive<usize>) -> Self {
+        Self {
+            start: *range.start(),
+            end: range.end().saturating_add(1),
+        }
+    }
+
+    /// Get span length
+    pub fn len(&self) -> usize {
+        self.end.saturating_sub(self.start)
+    }
+
+    /// Check if span is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl From<Range<usize>> for Span {
+    fn from(range: Range<usize>) -> Self {
+        Self {
+            start: range.start,
+            end: range.end,
+        }
+    }
+}
+
+impl From<RangeInclusive<usize>> for Span {
+    fn from(range: RangeInclusive<usize>) -> Self {
+        Self::inclusive(range)
+    }
+}
+
+impl From<Span> for Range<usize> {
+    fn from(span: Span) -> Self {
+        span.start..span.end
+    }
+}
@@ -1,21 +1,173 @@
-use super::Severity;
+use super::{NoteKind, Severity, StyleRole};
+use std::collections::HashMap;
 use termcolor::{Color, ColorSpec};
 
 /// Set of styles to colorize the output
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug)]
 pub struct Stylesheet {
+    /// Color of [`Severity::Help`] annotations and help messages
+    pub help: ColorSpec,
+    /// Color of [`Severity::Note`] annotations and notes
+    pub note: ColorSpec,
     /// Color of [`Severity::Info`] annotations
     pub info: ColorSpec,
     /// Color of [`Severity::Warning`] annotations
     pub warning: ColorSpec,
     /// Color of [`Severity::Error`] annotations
     pub error: ColorSpec,
+    /// Colors of [`Severity::Custom`] annotations by severity name. Custom severities without
+    /// a color here use the color of the most severe built-in severity not more severe than
+    /// them.
+    /// ```rust
+    /// # use show_my_errors::{Severity, Stylesheet};
+    /// # use termcolor::{Color, ColorSpec};
+    /// let mut stylesheet = Stylesheet::colored();
+    /// let mut security = ColorSpec::new();
+    /// security.set_bold(true).set_fg(Some(Color::Magenta));
+    /// stylesheet.custom.insert("security".into(), security.clone());
+    /// let severity = Severity::Custom { name: "security", rank: 450 };
+    /// assert_eq!(stylesheet.by_severity(&severity), &security);
+    /// let deprecation = Severity::Custom { name: "deprecation", rank: 350 };
+    /// assert_eq!(stylesheet.by_severity(&deprecation), &stylesheet.info);
+    /// ```
+    pub custom: HashMap<String, ColorSpec>,
+    /// Color of diagnostic code in the header, e.g. `E0308` in `error[E0308]`. Defaults to
+    /// `None`, so the code uses severity color like the rest of the header.
+    pub code: Option<ColorSpec>,
     /// Color of line numbers column
     pub linenr: ColorSpec,
     /// Color of filename
     pub filename: ColorSpec,
+    /// Color of the directory part of filename. If set, filename is split at the last path
+    /// separator, and only the part after it uses [`filename`](Stylesheet::filename) color.
+    /// ```rust
+    /// # use show_my_errors::Stylesheet;
+    /// # use termcolor::ColorSpec;
+    /// let mut stylesheet = Stylesheet::colored();
+    /// let mut directory = stylesheet.filename.clone();
+    /// directory.set_bold(false).set_dimmed(true);
+    /// stylesheet.directory = Some(directory);
+    /// ```
+    pub directory: Option<ColorSpec>,
     /// Color of annotated line content
     pub content: ColorSpec,
+    /// Names of severities shown in the output
+    pub labels: Labels,
+    /// Highlight annotated fragment of the line content with severity color, in addition
+    /// to carets below it. Only the annotation being shown is highlighted, so overlapping
+    /// annotations on the same line don't interfere with each other.
+    pub highlight_span: bool,
+    /// Show whitespace inside annotated fragment of the line content as visible glyphs:
+    /// `·` for space and `→` for tab (`.` and `>` with [`ascii_only`](Stylesheet::ascii_only)).
+    /// Useful for annotating trailing whitespace.
+    pub visible_whitespace: bool,
+    /// Make the first and the last caret under annotated fragment bold and the ones between
+    /// them not bold, so the extent of long fragments is easier to see. Disabled by default,
+    /// so all carets use the same severity color.
+    pub bold_caret_edges: bool,
+    /// Underline carets under annotated fragment, so they're distinguishable without relying
+    /// on color. Annotation text and headers are not underlined. Disabled by default.
+    pub underline_carets: bool,
+    /// Make [documentation links](crate::Annotation::url) clickable using OSC 8 escape
+    /// sequences. They are only added to colored output. Disabled by default, since not all
+    /// terminals support them.
+    pub hyperlinks: bool,
+    /// Use only ASCII for all decorations (gutter, arrow, carets, whitespace markers),
+    /// regardless of other settings. Source text and messages are shown as is.
+    pub ascii_only: bool,
+}
+
+impl Default for Stylesheet {
+    fn default() -> Self {
+        Self {
+            help: ColorSpec::new(),
+            note: ColorSpec::new(),
+            info: ColorSpec::new(),
+            warning: ColorSpec::new(),
+            error: ColorSpec::new(),
+            custom: HashMap::new(),
+            code: None,
+            linenr: ColorSpec::new(),
+            filename: ColorSpec::new(),
+            directory: None,
+            content: ColorSpec::new(),
+            labels: Labels::default(),
+            highlight_span: false,
+            visible_whitespace: false,
+            bold_caret_edges: false,
+            underline_carets: false,
+            hyperlinks: false,
+            ascii_only: false,
+        }
+    }
+}
+
+/// Names of severities shown in the output. Defaults to the English ones, i.e. the same that
+/// [`Severity`]s [`Display`](std::fmt::Display) implementation uses.
+/// ```rust
+/// # use show_my_errors::{AnnotationList, Stylesheet};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut stylesheet = Stylesheet::monochrome();
+/// stylesheet.labels.error = "erreur".into();
+/// let mut list = AnnotationList::new("bonjour.txt", "Bonjour");
+/// list.error(0..7, "message", None)?;
+/// let output = String::from_utf8(list.to_ansi_bytes(&stylesheet)?)?;
+/// assert!(output.contains("erreur: message"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Labels {
+    /// Name of [`Severity::Info`]
+    pub info: String,
+    /// Name of [`Severity::Warning`]
+    pub warning: String,
+    /// Name of [`Severity::Error`]
+    pub error: String,
+    /// Name of [`Severity::Note`] and [`NoteKind::Note`]
+    pub note: String,
+    /// Name of [`Severity::Help`] and [`NoteKind::Help`]
+    pub help: String,
+    /// Errors part of the summary footer, see
+    /// [`RenderOptions::summary`](crate::RenderOptions::summary). `{count}` is replaced with
+    /// the number of errors and `{s}` with `s` unless there is exactly one error.
+    pub errors_summary: String,
+    /// Warnings part of the summary footer, with the same placeholders as
+    /// [`errors_summary`](Labels::errors_summary)
+    pub warnings_summary: String,
+}
+
+impl Default for Labels {
+    fn default() -> Self {
+        Self {
+            info: Severity::Info.to_string(),
+            warning: Severity::Warning.to_string(),
+            error: Severity::Error.to_string(),
+            note: Severity::Note.to_string(),
+            help: Severity::Help.to_string(),
+            errors_summary: "aborting due to {count} previous error{s}".into(),
+            warnings_summary: "{count} warning{s} emitted".into(),
+        }
+    }
+}
+
+impl Labels {
+    /// Get name of the [`Severity`]
+    pub fn by_severity(&self, severity: &Severity) -> &str {
+        match severity {
+            Severity::Help => &self.help,
+            Severity::Note => &self.note,
+            Severity::Info => &self.info,
+            Severity::Warning => &self.warning,
+            Severity::Error => &self.error,
+            Severity::Custom { name, .. } => name,
+        }
+    }
+
+    /// Get name of the [`NoteKind`], same as of the corresponding [`Severity`]
+    pub fn by_note_kind(&self, kind: NoteKind) -> &str {
+        self.by_severity(&kind.into())
+    }
 }
 
 impl Stylesheet {
@@ -27,33 +179,147 @@ impl Stylesheet {
 
     /// Get a default rustc-like colored stylesheet
     pub fn colored() -> Self {
+        let mut help = ColorSpec::new();
+        let mut note = ColorSpec::new();
         let mut info = ColorSpec::new();
         let mut warning = ColorSpec::new();
         let mut error = ColorSpec::new();
         let mut linenr = ColorSpec::new();
         let mut filename = ColorSpec::new();
         let content = ColorSpec::new();
+        help.set_bold(true).set_fg(Some(Color::Cyan));
+        note.set_bold(true).set_fg(Some(Color::Green));
         info.set_bold(true);
         warning.set_bold(true).set_fg(Some(Color::Yellow));
         error.set_bold(true).set_fg(Some(Color::Red));
         linenr.set_bold(true).set_fg(Some(Color::Blue));
         filename.set_bold(true);
         Self {
+            help,
+            note,
             info,
             warning,
             error,
             linenr,
             filename,
             content,
+            ..Self::default()
+        }
+    }
+
+    /// Get a stylesheet using the [Dracula](https://draculatheme.com) palette: red errors,
+    /// orange warnings, cyan infos, green notes, pink helps, line numbers in the comment color and purple filenames.
+    /// Colors are 24-bit, so this requires a terminal with truecolor support.
+    pub fn dracula() -> Self {
+        let rgb = |r, g, b| Some(Color::Rgb(r, g, b));
+        let mut help = ColorSpec::new();
+        let mut note = ColorSpec::new();
+        let mut info = ColorSpec::new();
+        let mut warning = ColorSpec::new();
+        let mut error = ColorSpec::new();
+        let mut linenr = ColorSpec::new();
+        let mut filename = ColorSpec::new();
+        help.set_bold(true).set_fg(rgb(0xff, 0x79, 0xc6));
+        note.set_bold(true).set_fg(rgb(0x50, 0xfa, 0x7b));
+        info.set_bold(true).set_fg(rgb(0x8b, 0xe9, 0xfd));
+        warning.set_bold(true).set_fg(rgb(0xff, 0xb8, 0x6c));
+        error.set_bold(true).set_fg(rgb(0xff, 0x55, 0x55));
+        linenr.set_fg(rgb(0x62, 0x72, 0xa4));
+        filename.set_bold(true).set_fg(rgb(0xbd, 0x93, 0xf9));
+        Self {
+            help,
+            note,
+            info,
+            warning,
+            error,
+            linenr,
+            filename,
+            ..Self::default()
+        }
+    }
+
+    /// Get a high-contrast stylesheet for accessibility: decorations and messages are bold
+    /// and use intense (bright) variants of the basic terminal colors, while line content
+    /// keeps the terminal's default color. Annotated fragments are highlighted in addition
+    /// to carets and carets are underlined, so they're distinguishable without relying on
+    /// color alone.
+    pub fn high_contrast() -> Self {
+        let mut help = ColorSpec::new();
+        let mut note = ColorSpec::new();
+        let mut info = ColorSpec::new();
+        let mut warning = ColorSpec::new();
+        let mut error = ColorSpec::new();
+        let mut linenr = ColorSpec::new();
+        let mut filename = ColorSpec::new();
+        help.set_bold(true)
+            .set_intense(true)
+            .set_fg(Some(Color::Magenta));
+        note.set_bold(true)
+            .set_intense(true)
+            .set_fg(Some(Color::Green));
+        info.set_bold(true)
+            .set_intense(true)
+            .set_fg(Some(Color::Cyan));
+        warning
+            .set_bold(true)
+            .set_intense(true)
+            .set_fg(Some(Color::Yellow));
+        error
+            .set_bold(true)
+            .set_intense(true)
+            .set_fg(Some(Color::Red));
+        linenr
+            .set_bold(true)
+            .set_intense(true)
+            .set_fg(Some(Color::Blue));
+        filename.set_bold(true).set_intense(true);
+        Self {
+            help,
+            note,
+            info,
+            warning,
+            error,
+            linenr,
+            filename,
+            highlight_span: true,
+            underline_carets: true,
+            ..Self::default()
+        }
+    }
+
+    /// Get color of rendered text by its [`StyleRole`]. For [`StyleRole::Underline`],
+    /// [`StyleRole::Caret`], [`StyleRole::CaretEdge`] and [`StyleRole::CaretMiddle`] this is
+    /// the severity color, underline and bold attributes are changed when writing.
+    pub fn by_role(&self, role: StyleRole) -> &ColorSpec {
+        match role {
+            StyleRole::Severity(severity)
+            | StyleRole::Underline(severity)
+            | StyleRole::Caret(severity)
+            | StyleRole::CaretEdge(severity)
+            | StyleRole::CaretMiddle(severity) => self.by_severity(&severity),
+            StyleRole::Code(severity) => self
+                .code
+                .as_ref()
+                .unwrap_or_else(|| self.by_severity(&severity)),
+            StyleRole::LineNumber => &self.linenr,
+            StyleRole::Filename => &self.filename,
+            StyleRole::Directory => self.directory.as_ref().unwrap_or(&self.filename),
+            StyleRole::Content | StyleRole::Link => &self.content,
         }
     }
 
     /// Get color of message by its [`Severity`]
     pub fn by_severity(&self, severity: &Severity) -> &ColorSpec {
         match severity {
+            Severity::Help => &self.help,
+            Severity::Note => &self.note,
             Severity::Info => &self.info,
             Severity::Warning => &self.warning,
             Severity::Error => &self.error,
+            Severity::Custom { name, .. } => self
+                .custom
+                .get(*name)
+                .unwrap_or_else(|| self.by_severity(&severity.builtin())),
         }
     }
 }
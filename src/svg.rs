@@ -0,0 +1,168 @@
+use crate::{checkstyle::escape_xml, render::role_spec, AnnotationList, RenderOptions, Stylesheet};
+use std::fmt::Write;
+use termcolor::{Color, ColorSpec};
+
+/// Font size of the text, in pixels
+const FONT_SIZE: f64 = 14.0;
+/// Width of a single column of monospace text, in pixels
+const COLUMN_WIDTH: f64 = FONT_SIZE * 0.6;
+/// Distance between baselines of lines, in pixels
+const LINE_HEIGHT: f64 = FONT_SIZE * 1.3;
+/// Space around the text, in pixels
+const PADDING: f64 = 10.0;
+/// Background and default text colors, like in a dark terminal
+const BACKGROUND: &str = "#1e1e1e";
+const FOREGROUND: &str = "#d4d4d4";
+
+/// Basic terminal colors, normal and intense
+const PALETTE: [[(u8, u8, u8); 8]; 2] = [
+    [
+        (0x00, 0x00, 0x00),
+        (0xcd, 0x31, 0x31),
+        (0x0d, 0xbc, 0x79),
+        (0xe5, 0xe5, 0x10),
+        (0x24, 0x72, 0xc8),
+        (0xbc, 0x3f, 0xbc),
+        (0x11, 0xa8, 0xcd),
+        (0xe5, 0xe5, 0xe5),
+    ],
+    [
+        (0x66, 0x66, 0x66),
+        (0xf1, 0x4c, 0x4c),
+        (0x23, 0xd1, 0x8b),
+        (0xf5, 0xf5, 0x43),
+        (0x3b, 0x8e, 0xea),
+        (0xd6, 0x70, 0xd6),
+        (0x29, 0xb8, 0xdb),
+        (0xff, 0xff, 0xff),
+    ],
+];
+
+/// RGB value of a terminal color, or `None` for colors unknown to this version
+fn rgb(color: &Color, intense: bool) -> Option<(u8, u8, u8)> {
+    let basic = |idx: usize| PALETTE[usize::from(intense)][idx];
+    let rgb = match *color {
+        Color::Black => basic(0),
+        Color::Red => basic(1),
+        Color::Green => basic(2),
+        Color::Yellow => basic(3),
+        Color::Blue => basic(4),
+        Color::Magenta => basic(5),
+        Color::Cyan => basic(6),
+        Color::White => basic(7),
+        Color::Ansi256(idx @ 0..=15) => PALETTE[usize::from(idx >= 8)][usize::from(idx % 8)],
+        Color::Ansi256(idx @ 16..=231) => {
+            let level = |value: u8| if value == 0 { 0 } else { value * 40 + 55 };
+            let idx = idx - 16;
+            (level(idx / 36), level(idx / 6 % 6), level(idx % 6))
+        }
+        Color::Ansi256(idx) => {
+            let gray = (idx - 232) * 10 + 8;
+            (gray, gray, gray)
+        }
+        Color::Rgb(r, g, b) => (r, g, b),
+        _ => return None,
+    };
+    Some(rgb)
+}
+
+/// Presentation attributes of `<tspan>` for the style
+fn attributes(spec: &ColorSpec) -> String {
+    let mut attributes = String::new();
+    if let Some((r, g, b)) = spec.fg().and_then(|color| rgb(color, spec.intense())) {
+        let _ = write!(attributes, r##" fill="#{:02x}{:02x}{:02x}""##, r, g, b);
+    }
+    if spec.bold() {
+        attributes.push_str(r#" font-weight="bold""#);
+    }
+    if spec.italic() {
+        attributes.push_str(r#" font-style="italic""#);
+    }
+    if spec.underline() {
+        attributes.push_str(r#" text-decoration="underline""#);
+    }
+    if spec.dimmed() {
+        attributes.push_str(r#" opacity="0.7""#);
+    }
+    attributes
+}
+
+impl AnnotationList<'_> {
+    /// Render the message as a standalone SVG image: monospace text on a dark background,
+    /// colored using the stylesheet, like in a terminal. Background colors and
+    /// [`indent`](RenderOptions::indent) are not used. Image size is computed from the number
+    /// of lines and their width in columns, see [`RenderOptions::char_width`].
+    ///
+    /// Requires `svg` feature.
+    /// ```rust
+    /// # use show_my_errors::{AnnotationList, Stylesheet};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut list = AnnotationList::new("hello.txt", "Hello world!");
+    /// list.warning(4..7, "punctuation problem", "you probably forgot a comma")?;
+    /// let svg = list.to_svg(&Stylesheet::colored());
+    /// assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    /// assert!(svg.contains(r##"<tspan fill="#e5e510" font-weight="bold">warning: punctuation problem</tspan>"##));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_svg(&self, stylesheet: &Stylesheet) -> String {
+        self.to_svg_with_options(stylesheet, &RenderOptions::default())
+    }
+
+    /// Like [`.to_svg()`](AnnotationList::to_svg), but uses given layout settings
+    pub fn to_svg_with_options(&self, stylesheet: &Stylesheet, options: &RenderOptions) -> String {
+        let events = self.render_events_with_options(stylesheet, options);
+        let columns = events
+            .iter()
+            .map(|event| {
+                event
+                    .segments()
+                    .iter()
+                    .flat_map(|segment| segment.text.chars())
+                    .map(|c| options.display_width(c))
+                    .sum::<usize>()
+            })
+            .max()
+            .unwrap_or(0);
+        let width = columns as f64 * COLUMN_WIDTH + 2.0 * PADDING;
+        let height = events.len() as f64 * LINE_HEIGHT + 2.0 * PADDING;
+        let mut svg = format!(
+            concat!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w:.1}" height="{h:.1}" viewBox="0 0 {w:.1} {h:.1}">"#,
+                "\n",
+                r#"<rect width="100%" height="100%" fill="{bg}"/>"#,
+                "\n",
+                r#"<g font-family="monospace" font-size="{size}" fill="{fg}" xml:space="preserve">"#,
+                "\n",
+            ),
+            w = width,
+            h = height,
+            bg = BACKGROUND,
+            size = FONT_SIZE,
+            fg = FOREGROUND,
+        );
+        for (idx, event) in events.iter().enumerate() {
+            if event.segments().is_empty() {
+                continue;
+            }
+            // Baseline is placed so that descenders of the line fit above the next one
+            let y = PADDING + (idx as f64 + 0.8) * LINE_HEIGHT;
+            let _ = write!(svg, r#"<text x="{:.1}" y="{:.1}">"#, PADDING, y);
+            for segment in event.segments() {
+                if segment.text.is_empty() {
+                    continue;
+                }
+                let spec = role_spec(stylesheet, segment.role);
+                let _ = write!(
+                    svg,
+                    "<tspan{}>{}</tspan>",
+                    attributes(&spec),
+                    escape_xml(&segment.text)
+                );
+            }
+            svg.push_str("</text>\n");
+        }
+        svg.push_str("</g>\n</svg>\n");
+        svg
+    }
+}
@@ -0,0 +1,231 @@
+//! Rendering to lines of styled spans for TUI frameworks such as [ratatui]. Types here mirror
+//! the ones from `ratatui::style` and `ratatui::text`, so they can be converted without
+//! depending on a particular version of that crate:
+//! ```rust
+//! # mod ratatui {
+//! #     pub mod style {
+//! #         #[derive(Debug, Clone, Copy, PartialEq)]
+//! #         pub enum Color { Reset, Red, LightRed, Yellow, LightYellow, Blue, LightBlue }
+//! #         #[derive(Debug, Clone, Copy, PartialEq, Default)]
+//! #         pub struct Modifier(u16);
+//! #         impl Modifier {
+//! #             pub const BOLD: Self = Self(1);
+//! #             pub const UNDERLINED: Self = Self(8);
+//! #             pub fn empty() -> Self { Self(0) }
+//! #         }
+//! #         impl std::ops::BitOrAssign for Modifier {
+//! #             fn bitor_assign(&mut self, other: Self) { self.0 |= other.0 }
+//! #         }
+//! #         #[derive(Debug, Clone, Copy, PartialEq, Default)]
+//! #         pub struct Style {
+//! #             pub fg: Option<Color>,
+//! #             pub bg: Option<Color>,
+//! #             pub add_modifier: Modifier,
+//! #         }
+//! #     }
+//! #     pub mod text {
+//! #         use super::style::Style;
+//! #         pub struct Span<'a> { pub content: std::borrow::Cow<'a, str>, pub style: Style }
+//! #         impl<'a> Span<'a> {
+//! #             pub fn styled(content: String, style: Style) -> Self {
+//! #                 Self { content: content.into(), style }
+//! #             }
+//! #         }
+//! #         pub struct Line<'a> { pub spans: Vec<Span<'a>> }
+//! #         impl<'a> From<Vec<Span<'a>>> for Line<'a> {
+//! #             fn from(spans: Vec<Span<'a>>) -> Self { Self { spans } }
+//! #         }
+//! #     }
+//! # }
+//! use ratatui::{style::{Color, Modifier, Style}, text::{Line, Span}};
+//! use show_my_errors::{tui, AnnotationList, Stylesheet};
+//!
+//! fn convert_color(color: tui::Color) -> Color {
+//!     match color {
+//!         tui::Color::Red => Color::Red,
+//!         tui::Color::LightRed => Color::LightRed,
+//!         tui::Color::Yellow => Color::Yellow,
+//!         tui::Color::LightYellow => Color::LightYellow,
+//!         tui::Color::Blue => Color::Blue,
+//!         tui::Color::LightBlue => Color::LightBlue,
+//!         // ...
+//!         _ => Color::Reset,
+//!     }
+//! }
+//!
+//! fn convert_style(style: tui::Style) -> Style {
+//!     let mut add_modifier = Modifier::empty();
+//!     if style.add_modifier.bold {
+//!         add_modifier |= Modifier::BOLD;
+//!     }
+//!     if style.add_modifier.underlined {
+//!         add_modifier |= Modifier::UNDERLINED;
+//!     }
+//!     // ...
+//!     Style {
+//!         fg: style.fg.map(convert_color),
+//!         bg: style.bg.map(convert_color),
+//!         add_modifier,
+//!     }
+//! }
+//!
+//! fn convert(line: tui::Line) -> Line<'static> {
+//!     line.spans
+//!         .into_iter()
+//!         .map(|span| Span::styled(span.content, convert_style(span.style)))
+//!         .collect::<Vec<_>>()
+//!         .into()
+//! }
+//!
+//! # fn main() -> Result<(), show_my_errors::Error> {
+//! let mut list = AnnotationList::new("hello.txt", "Hello world!");
+//! list.error(4..7, "punctuation problem", "you probably forgot a comma")?;
+//! let lines = list.to_tui_lines(&Stylesheet::colored());
+//! let lines: Vec<Line> = lines.into_iter().map(convert).collect();
+//! assert_eq!(lines[0].spans[0].content, "error: punctuation problem");
+//! assert_eq!(lines[0].spans[0].style.fg, Some(Color::Red));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [ratatui]: https://docs.rs/ratatui
+use crate::{render::role_spec, AnnotationList, RenderOptions, Stylesheet};
+use termcolor::ColorSpec;
+
+/// Terminal color. Intense basic colors are the `Light*` ones, except for intense black and
+/// white, which are [`Color::DarkGray`] and [`Color::White`], while the normal ones are
+/// [`Color::Black`] and [`Color::Gray`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    Gray,
+    DarkGray,
+    LightRed,
+    LightGreen,
+    LightYellow,
+    LightBlue,
+    LightMagenta,
+    LightCyan,
+    White,
+    Rgb(u8, u8, u8),
+    Indexed(u8),
+}
+
+impl Color {
+    /// Convert a `termcolor` color, or return `None` for colors unknown to this version
+    fn from_termcolor(color: &termcolor::Color, intense: bool) -> Option<Self> {
+        use termcolor::Color as TermColor;
+        let color = match (*color, intense) {
+            (TermColor::Black, false) => Self::Black,
+            (TermColor::Black, true) => Self::DarkGray,
+            (TermColor::Red, false) => Self::Red,
+            (TermColor::Red, true) => Self::LightRed,
+            (TermColor::Green, false) => Self::Green,
+            (TermColor::Green, true) => Self::LightGreen,
+            (TermColor::Yellow, false) => Self::Yellow,
+            (TermColor::Yellow, true) => Self::LightYellow,
+            (TermColor::Blue, false) => Self::Blue,
+            (TermColor::Blue, true) => Self::LightBlue,
+            (TermColor::Magenta, false) => Self::Magenta,
+            (TermColor::Magenta, true) => Self::LightMagenta,
+            (TermColor::Cyan, false) => Self::Cyan,
+            (TermColor::Cyan, true) => Self::LightCyan,
+            (TermColor::White, false) => Self::Gray,
+            (TermColor::White, true) => Self::White,
+            (TermColor::Ansi256(idx), _) => Self::Indexed(idx),
+            (TermColor::Rgb(r, g, b), _) => Self::Rgb(r, g, b),
+            _ => return None,
+        };
+        Some(color)
+    }
+}
+
+/// Text attributes added by a style
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Modifier {
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underlined: bool,
+}
+
+/// Style of a span. `None` colors are the default ones of the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub add_modifier: Modifier,
+}
+
+impl From<&ColorSpec> for Style {
+    /// Intensity applies to both foreground and background colors, like in a terminal
+    fn from(spec: &ColorSpec) -> Self {
+        let color = |color: Option<&termcolor::Color>| {
+            color.and_then(|color| Color::from_termcolor(color, spec.intense()))
+        };
+        Self {
+            fg: color(spec.fg()),
+            bg: color(spec.bg()),
+            add_modifier: Modifier {
+                bold: spec.bold(),
+                dim: spec.dimmed(),
+                italic: spec.italic(),
+                underlined: spec.underline(),
+            },
+        }
+    }
+}
+
+/// Piece of text with a single style
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub content: String,
+    pub style: Style,
+}
+
+/// Line of the output, without the trailing newline
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Line {
+    pub spans: Vec<Span>,
+}
+
+impl AnnotationList<'_> {
+    /// Render the message as lines of styled spans, colored using the stylesheet. Line
+    /// content, carets and the gutter with line numbers are separate spans, adjacent text of
+    /// the same style is a single one, see [`.render_events()`](AnnotationList::render_events)
+    /// for the layout. [`indent`](RenderOptions::indent) is not used, since widgets are
+    /// positioned by the TUI framework. See [module docs](crate::tui) for conversion to
+    /// ratatui types.
+    ///
+    /// Requires `tui` feature.
+    pub fn to_tui_lines(&self, stylesheet: &Stylesheet) -> Vec<Line> {
+        self.to_tui_lines_with_options(stylesheet, &RenderOptions::default())
+    }
+
+    /// Like [`.to_tui_lines()`](AnnotationList::to_tui_lines), but uses given layout settings
+    pub fn to_tui_lines_with_options(
+        &self,
+        stylesheet: &Stylesheet,
+        options: &RenderOptions,
+    ) -> Vec<Line> {
+        self.render_events_with_options(stylesheet, options)
+            .iter()
+            .map(|event| Line {
+                spans: event
+                    .segments()
+                    .iter()
+                    .map(|segment| Span {
+                        content: segment.text.clone(),
+                        style: Style::from(&role_spec(stylesheet, segment.role)),
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+}